@@ -0,0 +1,114 @@
+//! Ways to follow the game without telling colors apart or seeing it.
+
+use std::io::{self, Write};
+
+use {Cells, GameState, GameboardController};
+
+/// Shape drawn on a tile in addition to its color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Marker {
+    Circle,
+    Square,
+    Triangle,
+    Diamond,
+}
+
+const MARKERS: [Marker; 4] = [Marker::Circle, Marker::Square, Marker::Triangle, Marker::Diamond];
+
+/// Shape and number of copies marking a tile with value `n`.
+///
+/// Shapes cycle with every doubling and one more copy is added each time
+/// they come round, so no two tiles up to 2048 share a marking.
+pub fn tile_marker(n: usize) -> (Marker, usize) {
+    let index = (n.max(2).trailing_zeros() as usize).saturating_sub(1);
+    (MARKERS[index % MARKERS.len()], index / MARKERS.len() + 1)
+}
+
+/// Writes every change of the board as plain text, for screen readers.
+pub struct MoveLog<W: Write> {
+    out: W,
+    last: Option<(usize, Cells, GameState)>,
+}
+
+impl<W: Write> MoveLog<W> {
+    /// Creates a log writing to `out`.
+    pub fn new(out: W) -> MoveLog<W> {
+        MoveLog {
+            out: out,
+            last: None,
+        }
+    }
+
+    /// Writes what changed since the last call, if anything.
+    pub fn observe(&mut self, controller: &GameboardController) -> io::Result<()> {
+        let gameboard = &controller.gameboard;
+        let current = (gameboard.moves, gameboard.cells.clone(), controller.game_state);
+        let headline = match self.last {
+            None => "New game.".to_string(),
+            Some(ref last) if *last == current => return Ok(()),
+            Some((moves, _, _)) => match controller.last_move {
+                Some(direction) if gameboard.moves == moves + 1 => {
+                    format!("Move {}: {}.", gameboard.moves, format!("{:?}", direction).to_lowercase())
+                }
+                _ => format!("Now at move {}.", gameboard.moves),
+            },
+        };
+        writeln!(self.out, "{} Score {}, highest tile {}.", headline, gameboard.score, gameboard.max_tile())?;
+        if let Some(milestone) = controller.milestone {
+            writeln!(self.out, "Reached {}!", milestone)?;
+        }
+        let state_changed = self.last.as_ref().is_none_or(|last| last.2 != controller.game_state);
+        if state_changed {
+            match controller.game_state {
+                GameState::Won => writeln!(self.out, "You won!")?,
+                GameState::Lost => writeln!(self.out, "You lost!")?,
+                GameState::Playing => (),
+            }
+        }
+        // One line per row from the board's `Display`, with empty cells named.
+        for (i, row) in gameboard.to_string().lines().enumerate() {
+            let cells: Vec<&str> = row.split('|').map(|cell| if cell == "0" { "empty" } else { cell }).collect();
+            writeln!(self.out, "Row {}: {}.", i + 1, cells.join(", "))?;
+        }
+        writeln!(self.out)?;
+        self.out.flush()?;
+        self.last = Some(current);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use accessibility::{tile_marker, MoveLog};
+    use {Command, GameboardController, MoveDirection};
+
+    #[test]
+    fn markers_differ_up_to_2048() {
+        let markers: Vec<_> = (1..12).map(|exponent| tile_marker(1 << exponent)).collect();
+        for (i, marker) in markers.iter().enumerate() {
+            assert!(!markers[i + 1..].contains(marker));
+        }
+    }
+
+    #[test]
+    fn log_reads_out_each_move() {
+        let mut controller = GameboardController::new("2|0|0\n0|0|0\n0|0|4".parse().unwrap());
+        let mut log = MoveLog::new(Vec::new());
+        log.observe(&controller).unwrap();
+        log.observe(&controller).unwrap();
+        controller.command(Command::Move(MoveDirection::Right));
+        log.observe(&controller).unwrap();
+        let text = String::from_utf8(log.out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(&lines[..5], &[
+            "New game. Score 0, highest tile 4.",
+            "Row 1: 2, empty, empty.",
+            "Row 2: empty, empty, empty.",
+            "Row 3: empty, empty, 4.",
+            "",
+        ]);
+        assert_eq!(lines[5], "Move 1: right. Score 0, highest tile 4.");
+        assert!(lines[6].starts_with("Row 1: ") && lines[6].ends_with(", 2."));
+        assert_eq!(lines.len(), 10);
+    }
+}
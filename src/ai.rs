@@ -0,0 +1,488 @@
+//! Expectimax player.
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use {Bitboard, BITBOARD_SIZE, Cell, Cells, Gameboard, MergeRule, MoveDirection, MoveTables, DIRECTIONS, MAX_EXPONENT};
+
+/// Thinking time that keeps hints and autoplay responsive.
+pub const INTERACTIVE_TIME_BUDGET: Duration = Duration::from_millis(150);
+
+/// Value of a position with no moves left.
+const LOST_VALUE: f64 = -1.0e6;
+/// Spawns less likely than this are scored without searching further.
+const MIN_PROBABILITY: f64 = 1.0e-4;
+
+/// Scores a position; higher is better for the player.
+pub trait Heuristic {
+    fn evaluate(&self, cells: &Cells) -> f64;
+}
+
+impl<F: Fn(&Cells) -> f64> Heuristic for F {
+    fn evaluate(&self, cells: &Cells) -> f64 {
+        self(cells)
+    }
+}
+
+/// Weighted sum of the built-in heuristics.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Heuristics {
+    pub empty_cells: f64,
+    pub monotonicity: f64,
+    pub smoothness: f64,
+    pub corner: f64,
+}
+
+impl Heuristics {
+    /// Creates weights that play well on the classic board.
+    pub fn new() -> Heuristics {
+        Heuristics {
+            empty_cells: 2.7,
+            monotonicity: 1.0,
+            smoothness: 0.1,
+            corner: 1.0,
+        }
+    }
+}
+
+impl Default for Heuristics {
+    fn default() -> Heuristics {
+        Heuristics::new()
+    }
+}
+
+impl Heuristic for Heuristics {
+    fn evaluate(&self, cells: &Cells) -> f64 {
+        self.empty_cells * empty_cells(cells) +
+            self.monotonicity * monotonicity(cells) +
+            self.smoothness * smoothness(cells) +
+            self.corner * corner_weight(cells)
+    }
+}
+
+/// Log2 of a tile's value, or 0 for an empty cell.
+fn rank(cell: &Cell) -> f64 {
+    match *cell {
+        Cell::Occupied(n) => (n as f64).log2(),
+        Cell::Empty => 0.0,
+    }
+}
+
+/// Lines of cells along both axes.
+fn lines(cells: &Cells) -> Vec<Vec<f64>> {
+    let height = cells.first().map_or(0, |column| column.len());
+    let mut lines: Vec<Vec<f64>> = cells.iter()
+        .map(|column| column.iter().map(rank).collect())
+        .collect();
+    for y in 0..height {
+        lines.push(cells.iter().map(|column| rank(&column[y])).collect());
+    }
+    lines
+}
+
+/// Number of empty cells.
+pub fn empty_cells(cells: &Cells) -> f64 {
+    cells.iter()
+        .flat_map(|column| column.iter())
+        .filter(|cell| **cell == Cell::Empty)
+        .count() as f64
+}
+
+/// Penalty for rows and columns that go up and down instead of one way;
+/// 0 when every line is sorted.
+pub fn monotonicity(cells: &Cells) -> f64 {
+    let mut total = 0.0;
+    for line in lines(cells) {
+        let mut increase = 0.0;
+        let mut decrease = 0.0;
+        for pair in line.windows(2) {
+            if pair[1] > pair[0] {
+                increase += pair[1] - pair[0];
+            } else {
+                decrease += pair[0] - pair[1];
+            }
+        }
+        total -= increase.min(decrease);
+    }
+    total
+}
+
+/// Penalty for neighbouring tiles with different values.
+pub fn smoothness(cells: &Cells) -> f64 {
+    let mut total = 0.0;
+    for line in lines(cells) {
+        let occupied: Vec<f64> = line.into_iter().filter(|&rank| rank > 0.0).collect();
+        for pair in occupied.windows(2) {
+            total -= (pair[1] - pair[0]).abs();
+        }
+    }
+    total
+}
+
+/// Rewards big tiles near one corner, using whichever corner fits best.
+pub fn corner_weight(cells: &Cells) -> f64 {
+    let width = cells.len();
+    let height = cells.first().map_or(0, |column| column.len());
+    let span = (width + height).saturating_sub(2).max(1) as f64;
+    let corners = [(0, 0), (width - 1, 0), (0, height - 1), (width - 1, height - 1)];
+    corners.iter().map(|&(corner_x, corner_y)| {
+        let mut total = 0.0;
+        for (x, column) in cells.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                let distance = (x as f64 - corner_x as f64).abs() + (y as f64 - corner_y as f64).abs();
+                total += rank(cell) * (span - distance) / span;
+            }
+        }
+        total
+    }).fold(f64::MIN, f64::max) / (width * height) as f64
+}
+
+/// A suggested move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hint {
+    pub direction: MoveDirection,
+    /// Expected value of every legal move, in the order of `DIRECTIONS`.
+    pub values: Vec<(MoveDirection, f64)>,
+}
+
+impl Hint {
+    /// Expected value of moving in `direction`, if that move is legal.
+    pub fn value(&self, direction: MoveDirection) -> Option<f64> {
+        self.values.iter().find(|&&(other, _)| other == direction).map(|&(_, value)| value)
+    }
+}
+
+/// Searches for the move with the highest expected heuristic value,
+/// averaging over the cells the rules can spawn.
+///
+/// Chance nodes place a single cell even when the rules spawn more. 4x4
+/// boards are searched as bitboards whenever no tile can reach the
+/// bitboard's limit within the search, with the same results.
+pub struct Expectimax<H = Heuristics> {
+    /// Number of own moves looked ahead.
+    pub depth: usize,
+    /// When set, searches one level deeper at a time until the budget runs
+    /// out, up to `depth`, and keeps the deepest finished result.
+    pub time_budget: Option<Duration>,
+    pub heuristic: H,
+}
+
+impl Expectimax {
+    /// Creates a player searching three moves ahead with the built-in heuristics.
+    pub fn new() -> Expectimax {
+        Expectimax::with_heuristic(Heuristics::new())
+    }
+}
+
+impl Default for Expectimax {
+    fn default() -> Expectimax {
+        Expectimax::new()
+    }
+}
+
+impl<H: Heuristic> Expectimax<H> {
+    /// Creates a player searching three moves ahead with `heuristic`.
+    pub fn with_heuristic(heuristic: H) -> Expectimax<H> {
+        Expectimax {
+            depth: 3,
+            time_budget: None,
+            heuristic: heuristic,
+        }
+    }
+
+    /// Expected value of every legal move, in the order of `DIRECTIONS`.
+    pub fn evaluate_moves(&self, gameboard: &Gameboard) -> Vec<(MoveDirection, f64)> {
+        let depth = ::std::cmp::max(1, self.depth);
+        let budget = match self.time_budget {
+            Some(budget) => budget,
+            None => return self.search(gameboard, depth, None).unwrap_or_default(),
+        };
+        let deadline = Instant::now() + budget;
+        // Depth 1 always finishes so there is a move to fall back on.
+        let mut best = self.search(gameboard, 1, None).unwrap_or_default();
+        for depth in 2..depth + 1 {
+            match self.search(gameboard, depth, Some(deadline)) {
+                Some(values) => best = values,
+                None => break,
+            }
+        }
+        best
+    }
+
+    /// Move with the highest expected value, or `None` if no move changes the board.
+    pub fn best_move(&self, gameboard: &Gameboard) -> Option<MoveDirection> {
+        self.hint(gameboard).map(|hint| hint.direction)
+    }
+
+    /// Best move along with the values of all legal moves, or `None` if no
+    /// move changes the board.
+    pub fn hint(&self, gameboard: &Gameboard) -> Option<Hint> {
+        let values = self.evaluate_moves(gameboard);
+        let best = values.iter()
+            .fold(None, |best: Option<(MoveDirection, f64)>, &(direction, value)| match best {
+                Some((_, best_value)) if best_value >= value => best,
+                _ => Some((direction, value)),
+            });
+        best.map(|(direction, _)| Hint {
+            direction: direction,
+            values: values,
+        })
+    }
+
+    /// Values of the legal moves at `depth`, or `None` if `deadline` passed.
+    fn search(&self, gameboard: &Gameboard, depth: usize, deadline: Option<Instant>)
+            -> Option<Vec<(MoveDirection, f64)>> {
+        match pack(gameboard, depth) {
+            Some(board) => self.search_bitboard(gameboard, board, depth, deadline),
+            None => self.search_cells(gameboard, depth, deadline),
+        }
+    }
+
+    fn search_cells(&self, gameboard: &Gameboard, depth: usize, deadline: Option<Instant>)
+            -> Option<Vec<(MoveDirection, f64)>> {
+        let spawns = spawn_probabilities(gameboard);
+        let mut values = Vec::new();
+        for (direction, outcome) in gameboard.afterstates() {
+            let value = self.chance_node(gameboard, &spawns, outcome.cells, depth - 1, 1.0, deadline)?;
+            values.push((direction, value));
+        }
+        Some(values)
+    }
+
+    /// Best value over the moves from `cells`.
+    fn max_node(&self, gameboard: &Gameboard, spawns: &[(usize, f64)], cells: &Cells,
+                depth: usize, probability: f64, deadline: Option<Instant>) -> Option<f64> {
+        if let Some(deadline) = deadline {
+            if Instant::now() >= deadline {
+                return None;
+            }
+        }
+        let mut best = LOST_VALUE;
+        for &direction in DIRECTIONS.iter() {
+            if Gameboard::cells_can_move(cells, direction) {
+                let outcome = gameboard.move_command(direction, cells.clone());
+                let value = self.chance_node(gameboard, spawns, outcome.cells, depth - 1, probability, deadline)?;
+                best = best.max(value);
+            }
+        }
+        Some(best)
+    }
+
+    /// Expected value over the cells that can spawn into `cells`.
+    fn chance_node(&self, gameboard: &Gameboard, spawns: &[(usize, f64)], mut cells: Cells,
+                   depth: usize, probability: f64, deadline: Option<Instant>) -> Option<f64> {
+        let mut empty = Vec::new();
+        for (x, column) in cells.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                if *cell == Cell::Empty {
+                    empty.push((x, y));
+                }
+            }
+        }
+        if depth == 0 || empty.is_empty() || probability < MIN_PROBABILITY {
+            return Some(self.heuristic.evaluate(&cells));
+        }
+        let mut total = 0.0;
+        for &(x, y) in empty.iter() {
+            for &(value, chance) in spawns.iter() {
+                let chance = chance / empty.len() as f64;
+                cells[x][y] = Cell::Occupied(value);
+                total += chance * self.max_node(gameboard, spawns, &cells, depth, probability * chance, deadline)?;
+            }
+            cells[x][y] = Cell::Empty;
+        }
+        Some(total)
+    }
+}
+
+// Search over bitboards, visiting positions in the same order as over
+// cells so the sums come out the same.
+impl<H: Heuristic> Expectimax<H> {
+    fn search_bitboard(&self, gameboard: &Gameboard, board: Bitboard, depth: usize, deadline: Option<Instant>)
+            -> Option<Vec<(MoveDirection, f64)>> {
+        let tables = move_tables(gameboard.rules.merge_rule);
+        let spawns: Vec<(u8, f64)> = spawn_probabilities(gameboard).into_iter()
+            .map(|(value, chance)| (value.trailing_zeros() as u8, chance))
+            .collect();
+        let mut values = Vec::new();
+        for &direction in DIRECTIONS.iter() {
+            let (moved, _) = tables.apply(board, direction);
+            if moved != board {
+                let value = self.chance_node_bitboard(tables, &spawns, moved, depth - 1, 1.0, deadline)?;
+                values.push((direction, value));
+            }
+        }
+        Some(values)
+    }
+
+    fn max_node_bitboard(&self, tables: &MoveTables, spawns: &[(u8, f64)], board: Bitboard,
+                         depth: usize, probability: f64, deadline: Option<Instant>) -> Option<f64> {
+        if let Some(deadline) = deadline {
+            if Instant::now() >= deadline {
+                return None;
+            }
+        }
+        let mut best = LOST_VALUE;
+        for &direction in DIRECTIONS.iter() {
+            let (moved, _) = tables.apply(board, direction);
+            if moved != board {
+                let value = self.chance_node_bitboard(tables, spawns, moved, depth - 1, probability, deadline)?;
+                best = best.max(value);
+            }
+        }
+        Some(best)
+    }
+
+    fn chance_node_bitboard(&self, tables: &MoveTables, spawns: &[(u8, f64)], mut board: Bitboard,
+                            depth: usize, probability: f64, deadline: Option<Instant>) -> Option<f64> {
+        let empty = board.empty_count();
+        if depth == 0 || empty == 0 || probability < MIN_PROBABILITY {
+            return Some(self.heuristic.evaluate(&board.to_cells()));
+        }
+        let mut total = 0.0;
+        for x in 0..BITBOARD_SIZE {
+            for y in 0..BITBOARD_SIZE {
+                if board.exponent(x, y) != 0 {
+                    continue;
+                }
+                for &(exponent, chance) in spawns.iter() {
+                    let chance = chance / empty as f64;
+                    board.set_exponent(x, y, exponent);
+                    total += chance * self.max_node_bitboard(tables, spawns, board, depth, probability * chance, deadline)?;
+                }
+                board.set_exponent(x, y, 0);
+            }
+        }
+        Some(total)
+    }
+}
+
+/// Move tables for `merge_rule`, built the first time they are needed.
+fn move_tables(merge_rule: MergeRule) -> &'static MoveTables {
+    static ONCE_PER_MOVE: OnceLock<MoveTables> = OnceLock::new();
+    static CASCADING: OnceLock<MoveTables> = OnceLock::new();
+    match merge_rule {
+        MergeRule::OncePerMove => ONCE_PER_MOVE.get_or_init(|| MoveTables::new(MergeRule::OncePerMove)),
+        MergeRule::Cascading => CASCADING.get_or_init(|| MoveTables::new(MergeRule::Cascading)),
+    }
+}
+
+/// `gameboard` as a bitboard, if it is 4x4 and searching `depth` moves
+/// can't merge two tiles of 2^15, which bitboards keep apart.
+///
+/// Every move raises the highest exponent on the board by at most one, so
+/// the search is exact while the highest exponent, counting spawned
+/// values, plus `depth` stays within `MAX_EXPONENT`.
+fn pack(gameboard: &Gameboard, depth: usize) -> Option<Bitboard> {
+    let board = Bitboard::from_gameboard(gameboard)?;
+    let spawned = gameboard.rules.spawn.values.iter().map(|&(value, _)| value);
+    if spawned.clone().any(|value| value < 2 || !value.is_power_of_two()) {
+        return None;
+    }
+    let highest = spawned.chain(Some(gameboard.max_tile())).max().unwrap_or(0);
+    let highest_exponent = highest.max(1).trailing_zeros() as usize;
+    if highest_exponent + depth <= MAX_EXPONENT as usize {
+        Some(board)
+    } else {
+        None
+    }
+}
+
+/// Spawned values with their probabilities.
+fn spawn_probabilities(gameboard: &Gameboard) -> Vec<(usize, f64)> {
+    let values = &gameboard.rules.spawn.values;
+    let total: u32 = values.iter().map(|&(_, weight)| weight).sum();
+    values.iter()
+        .map(|&(value, weight)| (value, f64::from(weight) / f64::from(total)))
+        .collect()
+}
+
+/// Best move for `gameboard` using the default search.
+pub fn best_move(gameboard: &Gameboard) -> Option<MoveDirection> {
+    Expectimax::new().best_move(gameboard)
+}
+
+#[cfg(test)]
+mod tests {
+    use ai::{best_move, monotonicity, smoothness, pack, Expectimax};
+    use {Bitboard, Cells, GameState, Gameboard, MergeRule, MoveDirection};
+
+    fn cells(board: &str) -> Cells {
+        board.parse::<Gameboard>().unwrap().cells
+    }
+
+    #[test]
+    fn sorted_smooth_board_scores_better() {
+        let sorted = cells("2|4|8|16\n0|0|0|0\n0|0|0|0\n0|0|0|0");
+        let mixed = cells("16|2|8|4\n0|0|0|0\n0|0|0|0\n0|0|0|0");
+        assert_eq!(monotonicity(&sorted), 0.0);
+        assert!(monotonicity(&mixed) < 0.0);
+        assert!(smoothness(&sorted) > smoothness(&mixed));
+    }
+
+    #[test]
+    fn takes_the_only_merge() {
+        let gameboard: Gameboard = "1024|1024|2|4\n2|4|8|16\n4|8|16|32\n8|16|32|64".parse().unwrap();
+        assert_eq!(best_move(&gameboard).map(|direction| direction == MoveDirection::Left ||
+                                                         direction == MoveDirection::Right),
+                   Some(true));
+    }
+
+    #[test]
+    fn no_move_on_a_lost_board() {
+        let gameboard: Gameboard = "2|4|2\n4|2|4\n2|4|2".parse().unwrap();
+        assert_eq!(best_move(&gameboard), None);
+    }
+
+    #[test]
+    fn time_budget_still_returns_a_move() {
+        let gameboard = Gameboard::with_seed(3, true);
+        let mut ai = Expectimax::new();
+        ai.depth = 10;
+        ai.time_budget = Some(::std::time::Duration::from_millis(20));
+        assert!(ai.best_move(&gameboard).is_some());
+    }
+
+    #[test]
+    fn plays_a_game_past_256() {
+        let mut gameboard = Gameboard::with_seed(1, true);
+        let mut ai = Expectimax::new();
+        ai.depth = 1;
+        while let Some(direction) = ai.best_move(&gameboard) {
+            if gameboard.handle_move(direction) == GameState::Lost {
+                break;
+            }
+        }
+        assert!(gameboard.max_tile() >= 256);
+    }
+
+    #[test]
+    fn bitboard_search_matches_the_cells() {
+        let mut ai = Expectimax::new();
+        ai.depth = 1;
+        for &merge_rule in [MergeRule::OncePerMove, MergeRule::Cascading].iter() {
+            for seed in 0..4 {
+                let mut gameboard = Gameboard::with_seed(seed, true);
+                gameboard.rules.merge_rule = merge_rule;
+                for _ in 0..30 {
+                    let direction = match ai.best_move(&gameboard) {
+                        Some(direction) => direction,
+                        None => break,
+                    };
+                    gameboard.handle_move(direction);
+                }
+                let board = pack(&gameboard, 2).unwrap();
+                assert_eq!(ai.search_bitboard(&gameboard, board, 2, None), ai.search_cells(&gameboard, 2, None));
+            }
+        }
+    }
+
+    #[test]
+    fn boards_near_the_bitboard_limit_use_cells() {
+        let gameboard: Gameboard = "8192|0|0|0\n0|0|0|0\n0|0|0|0\n0|0|0|2".parse().unwrap();
+        assert_eq!(pack(&gameboard, 2), Bitboard::from_gameboard(&gameboard));
+        assert_eq!(pack(&gameboard, 3), None);
+        let small: Gameboard = "2|0|0\n0|0|0\n0|0|0".parse().unwrap();
+        assert_eq!(pack(&small, 1), None);
+    }
+}
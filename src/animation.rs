@@ -0,0 +1,225 @@
+//! Tweening of tiles between two boards.
+
+use {Cell, Cells, MoveOutcome, Position};
+
+/// Seconds a move takes to animate unless configured otherwise.
+pub const DEFAULT_ANIMATION_DURATION: f64 = 0.12;
+/// Part of the animation spent sliding; merges pop and spawns fade in
+/// during the rest.
+const SLIDE_PART: f64 = 0.6;
+/// How much bigger a merged tile gets at the height of its pop.
+const POP_SCALE: f64 = 0.2;
+/// Size a spawned tile starts growing from.
+const SPAWN_SCALE: f64 = 0.5;
+
+/// A tile to draw, placed in cell units so it can sit between cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileSprite {
+    pub value: usize,
+    /// Column, possibly fractional.
+    pub x: f64,
+    /// Row, possibly fractional.
+    pub y: f64,
+    /// Size relative to a resting tile.
+    pub scale: f64,
+    /// From 0 for invisible to 1 for opaque.
+    pub opacity: f64,
+}
+
+impl TileSprite {
+    fn at(value: usize, x: f64, y: f64) -> TileSprite {
+        TileSprite {
+            value: value,
+            x: x,
+            y: y,
+            scale: 1.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// Sprites of every tile on `cells`, each resting in its cell.
+pub fn still_sprites(cells: &Cells) -> Vec<TileSprite> {
+    let mut sprites = Vec::new();
+    for (x, column) in cells.iter().enumerate() {
+        for (y, cell) in column.iter().enumerate() {
+            if let Cell::Occupied(value) = *cell {
+                sprites.push(TileSprite::at(value, x as f64, y as f64));
+            }
+        }
+    }
+    sprites
+}
+
+/// Slows down towards the end, so tiles settle into place.
+fn ease_out(t: f64) -> f64 {
+    1.0 - (1.0 - t) * (1.0 - t)
+}
+
+fn lerp(from: Position, to: Position, t: f64) -> (f64, f64) {
+    (from.x as f64 + (to.x as f64 - from.x as f64) * t,
+     from.y as f64 + (to.y as f64 - from.y as f64) * t)
+}
+
+/// Tiles of one move in flight: slides and merging tiles travel first,
+/// then merged tiles pop and new tiles fade in.
+#[derive(Clone, Debug)]
+pub struct MoveAnimation {
+    pub outcome: MoveOutcome,
+    /// Cells filled after the move, with the value they got.
+    pub spawns: Vec<(Position, usize)>,
+    /// Seconds the animation lasts.
+    pub duration: f64,
+    /// Seconds played so far.
+    pub elapsed: f64,
+}
+
+impl MoveAnimation {
+    /// Animates the move described by `outcome`, which ended up as `cells`
+    /// once new tiles were added.
+    pub fn new(outcome: MoveOutcome, cells: &Cells, duration: f64) -> MoveAnimation {
+        let mut spawns = Vec::new();
+        for (x, column) in cells.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                if let (Cell::Occupied(value), Cell::Empty) = (*cell, outcome.cells[x][y]) {
+                    spawns.push((Position::new(x, y), value));
+                }
+            }
+        }
+        MoveAnimation {
+            outcome: outcome,
+            spawns: spawns,
+            duration: duration,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Plays `dt` more seconds.
+    pub fn update(&mut self, dt: f64) {
+        self.elapsed += dt;
+    }
+
+    /// How far along the animation is, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.duration).min(1.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    /// Whether the tile resting at `position` on the final board is drawn
+    /// by the animation instead.
+    fn is_animated(&self, position: Position) -> bool {
+        self.outcome.slides.iter().any(|slide| slide.to == position) ||
+            self.outcome.was_merged(position) ||
+            self.spawns.iter().any(|&(spawn, _)| spawn == position)
+    }
+
+    /// Sprites to draw at this point of the animation, given the final
+    /// board `cells`.
+    pub fn sprites(&self, cells: &Cells) -> Vec<TileSprite> {
+        let progress = self.progress();
+        let mut sprites: Vec<TileSprite> = still_sprites(cells).into_iter()
+            .filter(|sprite| !self.is_animated(Position::new(sprite.x as usize, sprite.y as usize)))
+            .collect();
+
+        if progress < SLIDE_PART {
+            let t = ease_out(progress / SLIDE_PART);
+            for slide in self.outcome.slides.iter() {
+                let (x, y) = lerp(slide.from, slide.to, t);
+                sprites.push(TileSprite::at(slide.value, x, y));
+            }
+            for merge in self.outcome.merges.iter() {
+                for &source in merge.sources.iter() {
+                    let (x, y) = lerp(source, merge.target, t);
+                    sprites.push(TileSprite::at(merge.value / 2, x, y));
+                }
+            }
+            return sprites;
+        }
+
+        let t = (progress - SLIDE_PART) / (1.0 - SLIDE_PART);
+        for slide in self.outcome.slides.iter() {
+            sprites.push(TileSprite::at(slide.value, slide.to.x as f64, slide.to.y as f64));
+        }
+        // Cascading merges can hit a target more than once; its final tile pops once.
+        let mut popped: Vec<Position> = Vec::new();
+        for merge in self.outcome.merges.iter() {
+            let target = merge.target;
+            if popped.contains(&target) {
+                continue;
+            }
+            popped.push(target);
+            if let Cell::Occupied(value) = cells[target.x][target.y] {
+                let mut sprite = TileSprite::at(value, target.x as f64, target.y as f64);
+                sprite.scale = 1.0 + POP_SCALE * (t * ::std::f64::consts::PI).sin();
+                sprites.push(sprite);
+            }
+        }
+        for &(position, value) in self.spawns.iter() {
+            let mut sprite = TileSprite::at(value, position.x as f64, position.y as f64);
+            sprite.scale = SPAWN_SCALE + (1.0 - SPAWN_SCALE) * t;
+            sprite.opacity = t;
+            sprites.push(sprite);
+        }
+        sprites
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use animation::{still_sprites, MoveAnimation, TileSprite};
+    use {Gameboard, MoveDirection};
+
+    fn animate(board: &str, direction: MoveDirection) -> (Gameboard, MoveAnimation) {
+        let mut gameboard: Gameboard = board.parse().unwrap();
+        let outcome = gameboard.afterstate(direction).unwrap();
+        gameboard.handle_move(direction);
+        let animation = MoveAnimation::new(outcome, &gameboard.cells, 1.0);
+        (gameboard, animation)
+    }
+
+    fn find(sprites: &[TileSprite], value: usize) -> Vec<(f64, f64)> {
+        sprites.iter().filter(|sprite| sprite.value == value).map(|sprite| (sprite.x, sprite.y)).collect()
+    }
+
+    #[test]
+    fn tiles_travel_then_pop_and_fade_in() {
+        let (gameboard, mut animation) = animate("2|2|0|4\n0|0|0|0\n0|0|0|0\n0|0|0|0", MoveDirection::Left);
+        assert_eq!(animation.spawns.len(), 1);
+        let (spawn, _) = animation.spawns[0];
+
+        let start = animation.sprites(&gameboard.cells);
+        assert_eq!(find(&start, 2), vec![(0.0, 0.0), (1.0, 0.0)]);
+        assert_eq!(find(&start, 4), vec![(3.0, 0.0)]);
+
+        animation.update(0.3);
+        let middle = animation.sprites(&gameboard.cells);
+        let four = find(&middle, 4);
+        assert_eq!(four.len(), 1);
+        assert!(four[0].0 > 1.0 && four[0].0 < 3.0);
+
+        animation.update(0.5);
+        let end = animation.sprites(&gameboard.cells);
+        let merged = end.iter().find(|sprite| sprite.value == 4 && sprite.x == 0.0).unwrap();
+        assert!(merged.scale > 1.0);
+        let spawned = end.iter().find(|sprite| sprite.x == spawn.x as f64 && sprite.y == spawn.y as f64).unwrap();
+        assert!(spawned.opacity > 0.0 && spawned.opacity < 1.0);
+
+        animation.update(0.5);
+        assert!(animation.is_finished());
+        let mut finished = animation.sprites(&gameboard.cells);
+        let mut still = still_sprites(&gameboard.cells);
+        let order = |a: &TileSprite, b: &TileSprite| (a.x, a.y).partial_cmp(&(b.x, b.y)).unwrap();
+        finished.sort_by(order);
+        still.sort_by(order);
+        for (a, b) in finished.iter().zip(still.iter()) {
+            assert_eq!((a.value, a.x, a.y), (b.value, b.x, b.y));
+        }
+        assert_eq!(finished.len(), still.len());
+    }
+}
@@ -0,0 +1,105 @@
+//! Command line options shared by the front ends.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use {Expectimax, GameRng, Rules, UndoBudget, DEFAULT_ANIMATION_DURATION};
+use {is_supported_size, MIN_SIZE, MAX_SIZE};
+use ai::INTERACTIVE_TIME_BUDGET;
+
+/// Reads the value passed as `<name> <value>` on the command line, if any.
+pub fn arg_value(name: &str) -> Option<String> {
+    let mut args = ::std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+    }
+    None
+}
+
+/// Whether `name` was passed on the command line.
+pub fn has_flag(name: &str) -> bool {
+    ::std::env::args().skip(1).any(|arg| arg == name)
+}
+
+/// Prints `message` and exits, for options that can't be used.
+pub fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    ::std::process::exit(2)
+}
+
+/// Parses the `value` given for `name`, exiting with an error saying it
+/// must be `what` if it can't be.
+pub fn parse_arg<T: FromStr>(name: &str, value: &str, what: &str) -> T {
+    value.parse().unwrap_or_else(|_| exit_with_error(&format!("{} must be {}, got `{}`", name, what, value)))
+}
+
+/// Builds the spawn generator from `--seed <n>`, or a random seed if not given.
+pub fn rng_from_args() -> GameRng {
+    match arg_value("--seed") {
+        Some(seed) => GameRng::new(parse_arg("--seed", &seed, "a number")),
+        None => GameRng::from_entropy(),
+    }
+}
+
+/// Builds the rules from `--size <width>x<height>`, `--target <n>` and
+/// `--undos <n|none|unlimited>`, if given.
+pub fn rules_from_args() -> Rules {
+    let mut rules = Rules::new();
+    if let Some(size) = arg_value("--size") {
+        let dimensions: Vec<Option<usize>> = size.split('x').map(|n| n.parse().ok()).collect();
+        let (width, height) = match dimensions[..] {
+            [Some(side)] => (side, side),
+            [Some(width), Some(height)] => (width, height),
+            _ => exit_with_error("size must look like 5x5"),
+        };
+        rules.width = width;
+        rules.height = height;
+        if !is_supported_size(rules.width, rules.height) {
+            exit_with_error(&format!("{}x{} boards are not supported, use {}x{} to {}x{}",
+                rules.width, rules.height, MIN_SIZE, MIN_SIZE, MAX_SIZE, MAX_SIZE));
+        }
+    }
+    if let Some(target) = arg_value("--target") {
+        rules.win.target = parse_arg("--target", &target, "a number");
+        if rules.win.target == 0 {
+            exit_with_error("--target must be above 0");
+        }
+    }
+    if let Some(undos) = arg_value("--undos") {
+        rules.undo_budget = match undos.as_str() {
+            "none" => UndoBudget::Disabled,
+            "unlimited" => UndoBudget::Unlimited,
+            n => UndoBudget::PerGame(parse_arg("--undos", n, "a number, none or unlimited")),
+        };
+    }
+    rules
+}
+
+/// Builds the AI player from `--ai-depth <n>` and `--ai-time <ms>`, if given.
+pub fn ai_from_args() -> Expectimax {
+    let mut ai = Expectimax::new();
+    ai.time_budget = Some(INTERACTIVE_TIME_BUDGET);
+    if let Some(depth) = arg_value("--ai-depth") {
+        ai.depth = parse_arg("--ai-depth", &depth, "a number");
+    }
+    if let Some(time) = arg_value("--ai-time") {
+        ai.time_budget = Some(Duration::from_millis(parse_arg("--ai-time", &time, "a number of milliseconds")));
+    }
+    ai
+}
+
+/// Seconds a move takes to animate, from `--animation-ms <ms>` if given.
+pub fn animation_duration_from_args() -> f64 {
+    match arg_value("--animation-ms") {
+        Some(ms) => {
+            let ms: f64 = parse_arg("--animation-ms", &ms, "a number of milliseconds");
+            if !(ms >= 0.0 && ms.is_finite()) {
+                exit_with_error("--animation-ms must be a number of milliseconds from 0 up");
+            }
+            ms / 1000.0
+        }
+        None => DEFAULT_ANIMATION_DURATION,
+    }
+}
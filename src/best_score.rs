@@ -0,0 +1,84 @@
+//! Best score persistence.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// File the best score is kept in, relative to the working directory.
+pub const BEST_SCORE_FILE: &'static str = "best_score.txt";
+
+/// Highest score reached, optionally backed by a file.
+pub struct BestScore {
+    pub value: usize,
+    path: Option<PathBuf>,
+}
+
+impl BestScore {
+    /// Creates a best score that is only kept in memory.
+    pub fn new() -> BestScore {
+        BestScore {
+            value: 0,
+            path: None,
+        }
+    }
+
+    /// Loads the best score from `path`, starting from zero if it can't be read.
+    pub fn load<P: AsRef<Path>>(path: P) -> BestScore {
+        let path = path.as_ref().to_path_buf();
+        let mut contents = String::new();
+        let value = File::open(&path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .ok()
+            .and_then(|_| contents.trim().parse().ok())
+            .unwrap_or(0);
+        BestScore {
+            value: value,
+            path: Some(path),
+        }
+    }
+
+    /// Records `score` and saves it if it is a new best.
+    pub fn update(&mut self, score: usize) -> io::Result<()> {
+        if score <= self.value {
+            return Ok(());
+        }
+        self.value = score;
+        self.save()
+    }
+
+    /// Writes the best score to its file, if it has one.
+    pub fn save(&self) -> io::Result<()> {
+        match self.path {
+            Some(ref path) => {
+                let mut file = File::create(path)?;
+                writeln!(file, "{}", self.value)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for BestScore {
+    fn default() -> BestScore {
+        BestScore::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use BestScore;
+
+    #[test]
+    fn best_score_survives_reload() {
+        let path = env::temp_dir().join("piston_2048_best_score_test.txt");
+        let _ = fs::remove_file(&path);
+        let mut best_score = BestScore::load(&path);
+        assert_eq!(best_score.value, 0);
+        best_score.update(128).unwrap();
+        best_score.update(64).unwrap();
+        assert_eq!(BestScore::load(&path).value, 128);
+        fs::remove_file(&path).unwrap();
+    }
+}
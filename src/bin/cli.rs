@@ -0,0 +1,137 @@
+//! Plays 2048 in the terminal, reading one move per line from standard input.
+extern crate piston_2048;
+
+use std::io::{self, BufRead, Write};
+
+use piston_2048::{Gameboard, GameState, MoveDirection, Cell, BestScore, BEST_SCORE_FILE};
+use piston_2048::args::{rules_from_args, rng_from_args};
+
+const HELP: &'static str = "Moves: w/a/s/d, h/j/k/l, arrow keys or up/left/down/right. q quits.";
+
+/// A line of input.
+#[derive(Debug, PartialEq)]
+enum Command {
+    Move(MoveDirection),
+    Help,
+    Quit,
+}
+
+/// Reads a command from a single key, a word or an arrow key escape sequence.
+fn parse_command(input: &str) -> Option<Command> {
+    let command = match input.trim().to_lowercase().as_str() {
+        "w" | "k" | "up" | "\u{1b}[a" => Command::Move(MoveDirection::Up),
+        "d" | "l" | "right" | "\u{1b}[c" => Command::Move(MoveDirection::Right),
+        "s" | "j" | "down" | "\u{1b}[b" => Command::Move(MoveDirection::Down),
+        "a" | "h" | "left" | "\u{1b}[d" => Command::Move(MoveDirection::Left),
+        "?" | "help" => Command::Help,
+        "q" | "quit" | "exit" => Command::Quit,
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// Draws the board as a grid with right-aligned values.
+fn render_board(gameboard: &Gameboard) -> String {
+    let cell_width = ::std::cmp::max(4, gameboard.max_tile().to_string().len());
+    let separator = format!("+{}\n", format!("{}+", "-".repeat(cell_width + 2)).repeat(gameboard.width()));
+    let mut rendered = separator.clone();
+    for y in 0..gameboard.height() {
+        rendered.push('|');
+        for x in 0..gameboard.width() {
+            let value = match gameboard.cells[x][y] {
+                Cell::Occupied(n) => n.to_string(),
+                Cell::Empty => "".to_string(),
+            };
+            rendered.push_str(&format!(" {:>width$} |", value, width = cell_width));
+        }
+        rendered.push('\n');
+        rendered.push_str(&separator);
+    }
+    rendered
+}
+
+fn print_status(gameboard: &Gameboard, best_score: &BestScore) {
+    print!("{}", render_board(gameboard));
+    println!("Score: {}  Best: {}  Moves: {}  Max: {}",
+        gameboard.score, best_score.value, gameboard.moves, gameboard.max_tile());
+}
+
+fn main() {
+    let mut gameboard = Gameboard::with_rng(rules_from_args(), rng_from_args(), true);
+    let mut best_score = BestScore::load(BEST_SCORE_FILE);
+    println!("Seed: {}", gameboard.rng.seed());
+    println!("{}", HELP);
+    print_status(&gameboard, &best_score);
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().expect("Could not write to stdout");
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).expect("Could not read from stdin") == 0 {
+            break;
+        }
+        let move_direction = match parse_command(&line) {
+            Some(Command::Move(move_direction)) => move_direction,
+            Some(Command::Help) => {
+                println!("{}", HELP);
+                continue;
+            }
+            Some(Command::Quit) => break,
+            None => {
+                println!("Unknown command `{}`. {}", line.trim(), HELP);
+                continue;
+            }
+        };
+        let milestones_before = gameboard.milestones_reached.len();
+        let mut game_state = gameboard.handle_move(move_direction);
+        if let Err(err) = best_score.update(gameboard.score) {
+            println!("Could not save best score: {}", err);
+        }
+        print_status(&gameboard, &best_score);
+        for milestone in gameboard.milestones_reached[milestones_before..].iter() {
+            println!("Reached {}!", milestone);
+        }
+        match game_state {
+            GameState::Won => {
+                println!("You won!");
+                game_state = gameboard.state_after_win();
+            }
+            GameState::Lost => println!("You lost!"),
+            GameState::Playing => (),
+        }
+        if game_state != GameState::Playing {
+            break;
+        }
+    }
+    println!("Final score: {}", gameboard.score);
+}
+
+#[cfg(test)]
+mod tests {
+    use {parse_command, render_board, Command};
+    use piston_2048::{Gameboard, MoveDirection};
+
+    #[test]
+    fn keys_and_words_are_moves() {
+        assert_eq!(parse_command("w\n"), Some(Command::Move(MoveDirection::Up)));
+        assert_eq!(parse_command("Left"), Some(Command::Move(MoveDirection::Left)));
+        assert_eq!(parse_command("\u{1b}[B"), Some(Command::Move(MoveDirection::Down)));
+        assert_eq!(parse_command("q"), Some(Command::Quit));
+        assert_eq!(parse_command("jump"), None);
+    }
+
+    #[test]
+    fn board_is_rendered_as_a_grid() {
+        let gameboard: Gameboard = "2|0|0\n0|16|0\n0|0|2048".parse().unwrap();
+        assert_eq!(render_board(&gameboard), "\
++------+------+------+
+|    2 |      |      |
++------+------+------+
+|      |   16 |      |
++------+------+------+
+|      |      | 2048 |
++------+------+------+
+");
+    }
+}
@@ -0,0 +1,256 @@
+//! Plays seeded games with several strategies and compares the results.
+extern crate piston_2048;
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, Write};
+use std::thread;
+
+use piston_2048::{Expectimax, GameRng, Gameboard, MonteCarlo, Rules, Strategy};
+use piston_2048::args::{arg_value, parse_arg, rules_from_args};
+use piston_2048::strategy::{play, Corner, GameResult, Greedy, RandomStrategy};
+
+const DEFAULT_STRATEGIES: &'static str = "random,greedy,corner,expectimax,monte-carlo";
+const DEFAULT_CSV_FILE: &'static str = "tournament.csv";
+
+/// Settings shared by every game.
+struct Options {
+    games: usize,
+    seed: u64,
+    rules: Rules,
+    max_moves: Option<usize>,
+    ai_depth: usize,
+    rollouts: usize,
+    rollout_depth: Option<usize>,
+}
+
+impl Options {
+    fn from_args() -> Options {
+        let number = |name: &str, default: usize| match arg_value(name) {
+            Some(value) => parse_arg(name, &value, "a number"),
+            None => default,
+        };
+        Options {
+            games: number("--games", 100),
+            seed: number("--seed", 0) as u64,
+            rules: rules_from_args(),
+            max_moves: arg_value("--max-moves").map(|_| number("--max-moves", 0)),
+            ai_depth: number("--ai-depth", 2),
+            rollouts: number("--rollouts", 20),
+            rollout_depth: match arg_value("--rollout-depth") {
+                Some(ref depth) if depth == "full" => None,
+                Some(_) => Some(number("--rollout-depth", 0)),
+                None => Some(40),
+            },
+        }
+    }
+}
+
+/// Creates the strategy called `name`, seeded for one game.
+fn make_strategy(name: &str, seed: u64, options: &Options) -> Option<Box<dyn Strategy>> {
+    let strategy: Box<dyn Strategy> = match name {
+        "random" => Box::new(RandomStrategy::new(seed)),
+        "greedy" => Box::new(Greedy),
+        "corner" => Box::new(Corner),
+        "expectimax" => {
+            let mut expectimax = Expectimax::new();
+            expectimax.depth = options.ai_depth;
+            Box::new(expectimax)
+        }
+        "monte-carlo" => {
+            let mut monte_carlo = MonteCarlo::new(seed);
+            monte_carlo.rollouts = options.rollouts;
+            monte_carlo.rollout_depth = options.rollout_depth;
+            Box::new(monte_carlo)
+        }
+        _ => return None,
+    };
+    Some(strategy)
+}
+
+/// Plays every game of one strategy, spread over all cores.
+fn run_games(name: &str, options: &Options) -> Vec<GameResult> {
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut results: Vec<(usize, GameResult)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|worker| scope.spawn(move || {
+            (worker..options.games).step_by(threads).map(|game| {
+                let seed = options.seed + game as u64;
+                let gameboard = Gameboard::with_rng(options.rules.clone(), GameRng::new(seed), true);
+                let mut strategy = make_strategy(name, seed, options).expect("Strategy was checked");
+                (game, play(&mut *strategy, gameboard, options.max_moves))
+            }).collect::<Vec<_>>()
+        })).collect();
+        workers.into_iter().flat_map(|worker| worker.join().expect("A game panicked")).collect()
+    });
+    results.sort_by_key(|&(game, _)| game);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Results of one strategy.
+struct Summary {
+    name: String,
+    games: usize,
+    wins: usize,
+    /// Scores in increasing order.
+    scores: Vec<usize>,
+    total_moves: usize,
+    max_tiles: Vec<usize>,
+}
+
+impl Summary {
+    fn new(name: String, results: &[GameResult]) -> Summary {
+        let mut scores: Vec<usize> = results.iter().map(|result| result.score).collect();
+        scores.sort();
+        Summary {
+            name: name,
+            games: results.len(),
+            wins: results.iter().filter(|result| result.won).count(),
+            scores: scores,
+            total_moves: results.iter().map(|result| result.moves).sum(),
+            max_tiles: results.iter().map(|result| result.max_tile).collect(),
+        }
+    }
+
+    fn win_rate(&self) -> f64 {
+        self.wins as f64 / self.games.max(1) as f64
+    }
+
+    fn mean_score(&self) -> f64 {
+        self.scores.iter().sum::<usize>() as f64 / self.games.max(1) as f64
+    }
+
+    fn mean_moves(&self) -> f64 {
+        self.total_moves as f64 / self.games.max(1) as f64
+    }
+
+    /// Score below which a `fraction` of the games ended, by nearest rank.
+    fn percentile(&self, fraction: f64) -> usize {
+        if self.scores.is_empty() {
+            return 0;
+        }
+        let rank = (fraction * (self.scores.len() - 1) as f64).round() as usize;
+        self.scores[rank]
+    }
+
+    /// Games that ended with `tile` as their highest tile.
+    fn games_with_max_tile(&self, tile: usize) -> usize {
+        self.max_tiles.iter().filter(|&&max_tile| max_tile == tile).count()
+    }
+}
+
+/// Highest tiles reached by any strategy, in increasing order.
+fn tile_columns(summaries: &[Summary]) -> Vec<usize> {
+    let tiles: BTreeSet<usize> = summaries.iter().flat_map(|summary| summary.max_tiles.iter().cloned()).collect();
+    tiles.into_iter().collect()
+}
+
+fn print_table(summaries: &[Summary]) {
+    println!("{:<16} {:>6} {:>6} {:>8} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7}",
+             "strategy", "games", "win%", "mean", "min", "p25", "median", "p75", "max", "moves");
+    for summary in summaries {
+        println!("{:<16} {:>6} {:>6.1} {:>8.0} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7.0}",
+                 summary.name, summary.games, summary.win_rate() * 100.0, summary.mean_score(),
+                 summary.percentile(0.0), summary.percentile(0.25), summary.percentile(0.5),
+                 summary.percentile(0.75), summary.percentile(1.0), summary.mean_moves());
+    }
+    let tiles = tile_columns(summaries);
+    println!();
+    print!("{:<16}", "max tile");
+    for tile in tiles.iter() {
+        print!(" {:>6}", tile);
+    }
+    println!();
+    for summary in summaries {
+        print!("{:<16}", summary.name);
+        for &tile in tiles.iter() {
+            print!(" {:>6}", summary.games_with_max_tile(tile));
+        }
+        println!();
+    }
+}
+
+fn write_csv<W: Write>(out: &mut W, summaries: &[Summary]) -> io::Result<()> {
+    let tiles = tile_columns(summaries);
+    write!(out, "strategy,games,win_rate,mean_score,min_score,p25_score,median_score,p75_score,max_score,mean_moves")?;
+    for tile in tiles.iter() {
+        write!(out, ",max_tile_{}", tile)?;
+    }
+    writeln!(out)?;
+    for summary in summaries {
+        write!(out, "{},{},{:.4},{:.1},{},{},{},{},{},{:.1}",
+               summary.name, summary.games, summary.win_rate(), summary.mean_score(),
+               summary.percentile(0.0), summary.percentile(0.25), summary.percentile(0.5),
+               summary.percentile(0.75), summary.percentile(1.0), summary.mean_moves())?;
+        for &tile in tiles.iter() {
+            write!(out, ",{}", summary.games_with_max_tile(tile))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = Options::from_args();
+    let strategies = arg_value("--strategies").unwrap_or_else(|| DEFAULT_STRATEGIES.to_string());
+    let mut summaries = Vec::new();
+    for name in strategies.split(',') {
+        let strategy = match make_strategy(name, options.seed, &options) {
+            Some(strategy) => strategy,
+            None => {
+                println!("Unknown strategy `{}`. Choose from {}", name, DEFAULT_STRATEGIES);
+                return;
+            }
+        };
+        let results = run_games(name, &options);
+        summaries.push(Summary::new(strategy.name(), &results));
+    }
+    print_table(&summaries);
+
+    let csv_path = arg_value("--csv").unwrap_or_else(|| DEFAULT_CSV_FILE.to_string());
+    let written = File::create(&csv_path).and_then(|mut file| write_csv(&mut file, &summaries));
+    match written {
+        Ok(()) => println!("\nWrote {}", csv_path),
+        Err(err) => println!("\nCould not write {}: {}", csv_path, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {write_csv, Summary};
+    use piston_2048::strategy::GameResult;
+
+    fn result(score: usize, max_tile: usize, won: bool) -> GameResult {
+        GameResult {
+            score: score,
+            moves: score / 10,
+            max_tile: max_tile,
+            won: won,
+        }
+    }
+
+    fn summary() -> Summary {
+        let results = [result(400, 64, false), result(100, 32, false), result(3000, 256, true), result(800, 64, false)];
+        Summary::new("test".to_string(), &results)
+    }
+
+    #[test]
+    fn summary_reports_rates_and_percentiles() {
+        let summary = summary();
+        assert_eq!(summary.win_rate(), 0.25);
+        assert_eq!(summary.mean_score(), 1075.0);
+        assert_eq!(summary.percentile(0.0), 100);
+        assert_eq!(summary.percentile(0.5), 800);
+        assert_eq!(summary.percentile(1.0), 3000);
+        assert_eq!(summary.games_with_max_tile(64), 2);
+    }
+
+    #[test]
+    fn csv_has_a_column_per_max_tile() {
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &[summary()]).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "\
+strategy,games,win_rate,mean_score,min_score,p25_score,median_score,p75_score,max_score,mean_moves,max_tile_32,max_tile_64,max_tile_256
+test,4,0.2500,1075.0,100,400,800,800,3000,107.5,1,2,1
+");
+    }
+}
@@ -0,0 +1,300 @@
+//! Plays 2048 full-screen in the terminal.
+extern crate crossterm;
+extern crate piston_2048;
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::{cursor, execute, queue, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::ClearType;
+
+use piston_2048::{Cell, Command, GameboardController, GameState, Gameboard, MoveDirection};
+use piston_2048::{BestScore, BEST_SCORE_FILE, AUTOSAVE_FILE, REPLAY_FILE, load_replay};
+use piston_2048::args::{arg_value, has_flag, rules_from_args, rng_from_args, ai_from_args};
+use piston_2048::palette::{tile_color, to_rgb8};
+
+const HELP: &'static str = "Arrows/WASD/hjkl move  u undo  r redo  ? hint  p autoplay  F5 save  F9 load  q quit";
+const PLAYBACK_HELP: &'static str = "</> step  space play  +/- speed  Home/End  digits+Enter jump  q quit";
+
+/// Lines per tile; the value goes on the middle one.
+const TILE_HEIGHT: u16 = 3;
+/// Time between redraws while a replay is playing.
+const TICK: Duration = Duration::from_millis(50);
+
+const EMPTY_TILE_COLOR: Color = Color::Rgb { r: 60, g: 60, b: 60 };
+const TILE_TEXT_COLOR: Color = Color::Rgb { r: 0, g: 0, b: 0 };
+
+/// What a key press asks for.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Command(Command),
+    Quit,
+}
+
+/// Maps a key press to an action, using the same keys as the window where possible.
+fn key_action(key: KeyEvent) -> Option<Action> {
+    let command = if key.modifiers.contains(KeyModifiers::CONTROL) {
+        match key.code {
+            KeyCode::Char('z') => Command::Undo,
+            KeyCode::Char('y') => Command::Redo,
+            KeyCode::Char('c') => return Some(Action::Quit),
+            _ => return None,
+        }
+    } else {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            KeyCode::Up | KeyCode::Char('w') | KeyCode::Char('k') => Command::Move(MoveDirection::Up),
+            KeyCode::Right | KeyCode::Char('d') | KeyCode::Char('l') => Command::Move(MoveDirection::Right),
+            KeyCode::Down | KeyCode::Char('s') | KeyCode::Char('j') => Command::Move(MoveDirection::Down),
+            KeyCode::Left | KeyCode::Char('a') | KeyCode::Char('h') => Command::Move(MoveDirection::Left),
+            KeyCode::Char('u') => Command::Undo,
+            KeyCode::Char('r') => Command::Redo,
+            KeyCode::F(5) => Command::Quicksave,
+            KeyCode::F(6) => Command::SaveReplay,
+            KeyCode::F(9) => Command::Quickload,
+            KeyCode::Char('?') => Command::Hint,
+            KeyCode::Char('p') => Command::ToggleAutoplay,
+            KeyCode::Char('<') | KeyCode::Char(',') => Command::StepBack,
+            KeyCode::Char('>') | KeyCode::Char('.') => Command::StepForward,
+            KeyCode::Char(' ') => Command::TogglePlaying,
+            KeyCode::Char('+') | KeyCode::Char('=') => Command::Faster,
+            KeyCode::Char('-') => Command::Slower,
+            KeyCode::Home => Command::JumpToStart,
+            KeyCode::End => Command::JumpToEnd,
+            KeyCode::Char(c) if c.is_ascii_digit() => Command::JumpDigit(c as u32 - '0' as u32),
+            KeyCode::Backspace => Command::JumpErase,
+            KeyCode::Enter => Command::JumpConfirm,
+            _ => return None,
+        }
+    };
+    Some(Action::Command(command))
+}
+
+/// Characters per tile, wide enough for the largest value on the board.
+fn tile_width(gameboard: &Gameboard) -> usize {
+    ::std::cmp::max(6, gameboard.max_tile().to_string().len() + 2)
+}
+
+fn tile_background(cell: &Cell) -> Color {
+    match *cell {
+        Cell::Occupied(n) => {
+            let [r, g, b] = to_rgb8(tile_color(n));
+            Color::Rgb { r: r, g: g, b: b }
+        }
+        Cell::Empty => EMPTY_TILE_COLOR,
+    }
+}
+
+/// Text for the middle line of a tile, centered in `width` characters.
+fn tile_label(cell: &Cell, width: usize) -> String {
+    match *cell {
+        Cell::Occupied(n) => format!("{:^width$}", n, width = width),
+        Cell::Empty => " ".repeat(width),
+    }
+}
+
+/// Status lines shown below the board.
+fn status_lines(controller: &GameboardController) -> Vec<String> {
+    let gameboard = &controller.gameboard;
+    let mut lines = vec![format!("Score: {}  Best: {}  Moves: {}  Max: {}",
+        gameboard.score, controller.best_score.value, gameboard.moves, gameboard.max_tile())];
+    match controller.player {
+        Some(ref player) => {
+            lines.push(format!("Replay: move {}/{}  x{}  {}{}",
+                player.position, player.len(), player.speed,
+                if player.playing { "playing" } else { "paused" },
+                if controller.jump_input.is_empty() {
+                    "".to_string()
+                } else {
+                    format!("  go to: {}", controller.jump_input)
+                }));
+            lines.push(PLAYBACK_HELP.to_string());
+        }
+        None => {
+            lines.push(match controller.history.undos_remaining() {
+                Some(n) => format!("Undos left: {}", n),
+                None => "".to_string(),
+            });
+            lines.push(HELP.to_string());
+        }
+    }
+    if let Some(notice) = controller.notice() {
+        lines.push(notice);
+    }
+    lines
+}
+
+/// Redraws the whole screen over the previous frame.
+fn draw<W: Write>(out: &mut W, controller: &GameboardController) -> io::Result<()> {
+    let gameboard = &controller.gameboard;
+    let width = tile_width(gameboard);
+    let mut row = 0;
+    for y in 0..gameboard.height() {
+        for line in 0..TILE_HEIGHT {
+            queue!(out, cursor::MoveTo(0, row), SetForegroundColor(TILE_TEXT_COLOR))?;
+            for x in 0..gameboard.width() {
+                let cell = &gameboard.cells[x][y];
+                let text = if line == TILE_HEIGHT / 2 { tile_label(cell, width) } else { " ".repeat(width) };
+                queue!(out, SetBackgroundColor(tile_background(cell)), Print(text), ResetColor, Print(" "))?;
+            }
+            queue!(out, terminal::Clear(ClearType::UntilNewLine))?;
+            row += 1;
+        }
+        queue!(out, cursor::MoveTo(0, row), terminal::Clear(ClearType::UntilNewLine))?;
+        row += 1;
+    }
+    for line in status_lines(controller) {
+        queue!(out, cursor::MoveTo(0, row), Print(line), terminal::Clear(ClearType::UntilNewLine))?;
+        row += 1;
+    }
+    queue!(out, cursor::MoveTo(0, row), terminal::Clear(ClearType::FromCursorDown))?;
+
+    let dialog = match controller.game_state {
+        GameState::Won => Some(("You won!", "Move to keep playing")),
+        GameState::Lost => Some(("You lost!", "u to undo, q to quit")),
+        GameState::Playing => None,
+    };
+    if let Some((title, hint)) = dialog {
+        let board_width = (width + 1) * gameboard.width();
+        let board_height = (TILE_HEIGHT as usize + 1) * gameboard.height();
+        draw_dialog(out, title, hint, board_width, board_height)?;
+    }
+    out.flush()
+}
+
+/// Draws a box with `title` and `hint` centered over the board.
+fn draw_dialog<W: Write>(out: &mut W, title: &str, hint: &str,
+                         board_width: usize, board_height: usize) -> io::Result<()> {
+    let inner = ::std::cmp::max(title.len(), hint.len()) + 2;
+    let left = board_width.saturating_sub(inner + 2) / 2;
+    let top = board_height.saturating_sub(5) / 2;
+    let lines = [
+        format!("+{}+", "-".repeat(inner)),
+        format!("|{:^width$}|", title, width = inner),
+        format!("|{:^width$}|", hint, width = inner),
+        format!("+{}+", "-".repeat(inner)),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        queue!(out, cursor::MoveTo(left as u16, (top + i) as u16),
+            SetBackgroundColor(Color::White), SetForegroundColor(Color::Black), Print(line), ResetColor)?;
+    }
+    Ok(())
+}
+
+/// Puts the terminal back the way it was, even if the game panics.
+struct RawScreen;
+
+impl RawScreen {
+    fn enter() -> io::Result<RawScreen> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawScreen)
+    }
+}
+
+impl Drop for RawScreen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn run(controller: &mut GameboardController) -> io::Result<()> {
+    let _screen = RawScreen::enter()?;
+    let mut out = io::stdout();
+    execute!(out, terminal::Clear(ClearType::All))?;
+    let mut last_update = Instant::now();
+    let mut was_updating = false;
+    loop {
+        draw(&mut out, controller)?;
+        // Time spent idle, say on a paused replay, doesn't count once updates resume.
+        let updating = controller.needs_updates();
+        if updating && !was_updating {
+            last_update = Instant::now();
+        }
+        was_updating = updating;
+        // Playback and autoplay need to wake up without a key press.
+        if updating && !event::poll(TICK)? {
+            controller.update(last_update.elapsed().as_secs_f64());
+            last_update = Instant::now();
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                match key_action(key) {
+                    Some(Action::Quit) => return Ok(()),
+                    Some(Action::Command(command)) => controller.command(command),
+                    None => (),
+                }
+            }
+            Event::Resize(..) => execute!(out, terminal::Clear(ClearType::All))?,
+            _ => (),
+        }
+    }
+}
+
+fn main() {
+    let replay = arg_value("--replay").map(|path| load_replay(&path).expect("Could not load replay"));
+    let gameboard = Gameboard::with_rng(rules_from_args(), rng_from_args(), true);
+    let seed = gameboard.rng.seed();
+    let mut controller = GameboardController::new(gameboard);
+    controller.best_score = BestScore::load(BEST_SCORE_FILE);
+    let load_path = arg_value("--load").or_else(|| {
+        if has_flag("--resume") { Some(AUTOSAVE_FILE.to_string()) } else { None }
+    });
+    if let Some(path) = load_path {
+        controller.load(&path).expect("Could not load game");
+    }
+    let playback = replay.is_some();
+    if let Some(replay) = replay {
+        controller.start_playback(replay);
+    }
+    controller.autoplay = has_flag("--autoplay");
+    controller.ai = ai_from_args();
+
+    run(&mut controller).expect("Could not draw to the terminal");
+
+    if playback {
+        return;
+    }
+    println!("Seed: {}", seed);
+    println!("Final score: {}", controller.gameboard.score);
+    match controller.save(AUTOSAVE_FILE) {
+        Ok(()) => println!("Saved game to {}", AUTOSAVE_FILE),
+        Err(err) => println!("Could not save game: {}", err),
+    }
+    if controller.replay.is_some() {
+        match controller.save_replay(REPLAY_FILE) {
+            Ok(()) => println!("Saved replay to {}", REPLAY_FILE),
+            Err(err) => println!("Could not save replay: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {key_action, tile_label, Action};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use piston_2048::{Cell, Command, MoveDirection};
+
+    #[test]
+    fn keys_map_to_the_window_commands() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(key_action(key(KeyCode::Left)), Some(Action::Command(Command::Move(MoveDirection::Left))));
+        assert_eq!(key_action(key(KeyCode::Char('k'))), Some(Action::Command(Command::Move(MoveDirection::Up))));
+        assert_eq!(key_action(key(KeyCode::Char('<'))), Some(Action::Command(Command::StepBack)));
+        assert_eq!(key_action(key(KeyCode::Char('.'))), Some(Action::Command(Command::StepForward)));
+        assert_eq!(key_action(key(KeyCode::Char('7'))), Some(Action::Command(Command::JumpDigit(7))));
+        assert_eq!(key_action(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL)),
+                   Some(Action::Command(Command::Undo)));
+        assert_eq!(key_action(key(KeyCode::Char('q'))), Some(Action::Quit));
+    }
+
+    #[test]
+    fn tile_labels_are_centered() {
+        assert_eq!(tile_label(&Cell::Occupied(16), 6), "  16  ");
+        assert_eq!(tile_label(&Cell::Empty, 4), "    ");
+    }
+}
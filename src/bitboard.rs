@@ -0,0 +1,335 @@
+//! Packed 4x4 board for fast simulation, used by the expectimax search.
+
+use {Cell, Cells, Gameboard, GameRng, MergeRule, MoveDirection, SpawnCount, SpawnRules, DIRECTIONS};
+
+/// Cells along each side of a bitboard.
+pub const BITBOARD_SIZE: usize = 4;
+/// Highest exponent a cell can hold, so the largest tile is 2^15.
+///
+/// Two tiles of 2^15 don't merge on a bitboard, though they do on a
+/// `Gameboard`, so a bitboard only moves like the engine while there is
+/// at most one of them.
+pub const MAX_EXPONENT: u8 = 15;
+
+const ROW_MASK: u64 = 0xFFFF;
+
+/// A 4x4 board packed into 64 bits.
+///
+/// Every cell is a 4-bit exponent, 0 for an empty cell and `e` for a tile
+/// of value 2^e. Cell (x, y) is stored at bits `16 * y + 4 * x`, so every
+/// row is one `u16` with its leftmost cell in the lowest bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    /// Packs `cells`, or returns `None` if they aren't 4x4 or hold a value
+    /// that is not a power of two between 2 and 2^15.
+    pub fn from_cells(cells: &Cells) -> Option<Bitboard> {
+        if cells.len() != BITBOARD_SIZE || cells.iter().any(|column| column.len() != BITBOARD_SIZE) {
+            return None;
+        }
+        let mut board = Bitboard(0);
+        for (x, column) in cells.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                if let Cell::Occupied(n) = *cell {
+                    if n < 2 || !n.is_power_of_two() || n.trailing_zeros() > u32::from(MAX_EXPONENT) {
+                        return None;
+                    }
+                    board.set_exponent(x, y, n.trailing_zeros() as u8);
+                }
+            }
+        }
+        Some(board)
+    }
+
+    /// Packs the cells of `gameboard`.
+    pub fn from_gameboard(gameboard: &Gameboard) -> Option<Bitboard> {
+        Bitboard::from_cells(&gameboard.cells)
+    }
+
+    /// Unpacks the board into cells indexed `[x][y]`.
+    pub fn to_cells(&self) -> Cells {
+        (0..BITBOARD_SIZE).map(|x| {
+            (0..BITBOARD_SIZE).map(|y| match self.exponent(x, y) {
+                0 => Cell::Empty,
+                exponent => Cell::Occupied(1 << exponent),
+            }).collect()
+        }).collect()
+    }
+
+    /// Exponent of the cell at (x, y), 0 if it is empty.
+    pub fn exponent(&self, x: usize, y: usize) -> u8 {
+        ((self.0 >> (16 * y + 4 * x)) & 0xF) as u8
+    }
+
+    pub fn set_exponent(&mut self, x: usize, y: usize, exponent: u8) {
+        let shift = 16 * y + 4 * x;
+        self.0 = (self.0 & !(0xF << shift)) | (u64::from(exponent & 0xF) << shift);
+    }
+
+    /// Number of empty cells.
+    pub fn empty_count(&self) -> usize {
+        (0..16).filter(|i| (self.0 >> (4 * i)) & 0xF == 0).count()
+    }
+
+    /// Value of the highest tile, 0 on an empty board.
+    pub fn max_tile(&self) -> usize {
+        match (0..16).map(|i| (self.0 >> (4 * i)) & 0xF).max() {
+            Some(0) | None => 0,
+            Some(exponent) => 1 << exponent,
+        }
+    }
+
+    /// Swaps rows and columns.
+    pub fn transpose(&self) -> Bitboard {
+        let x = self.0;
+        let a1 = x & 0xF0F0_0F0F_F0F0_0F0F;
+        let a2 = x & 0x0000_F0F0_0000_F0F0;
+        let a3 = x & 0x0F0F_0000_0F0F_0000;
+        let a = a1 | (a2 << 12) | (a3 >> 12);
+        let b1 = a & 0xFF00_FF00_00FF_00FF;
+        let b2 = a & 0x00FF_00FF_0000_0000;
+        let b3 = a & 0x0000_0000_FF00_FF00;
+        Bitboard(b1 | (b2 >> 24) | (b3 << 24))
+    }
+
+    /// Places cells the way `Gameboard` does: for each spawned cell, an
+    /// empty position and then a value are drawn from `rng`, so a bitboard
+    /// and a gameboard sharing an rng stay in step.
+    pub fn spawn(&mut self, rng: &mut GameRng, spawn: &SpawnRules, count: SpawnCount) {
+        let cells_to_add = count.sample(rng);
+        for _ in 0..cells_to_add {
+            let empty = self.empty_count();
+            if empty == 0 {
+                break;
+            }
+            // Counts empty cells in the order of `Gameboard::empty_positions`.
+            let mut skip = rng.gen_range(0, empty);
+            let mut position = (0, 0);
+            'search: for x in 0..BITBOARD_SIZE {
+                for y in 0..BITBOARD_SIZE {
+                    if self.exponent(x, y) == 0 {
+                        if skip == 0 {
+                            position = (x, y);
+                            break 'search;
+                        }
+                        skip -= 1;
+                    }
+                }
+            }
+            let (x, y) = position;
+            let value = spawn.sample_value(rng);
+            self.set_exponent(x, y, value.trailing_zeros() as u8);
+        }
+    }
+}
+
+/// Results of moving every possible row, for one merge rule.
+pub struct MoveTables {
+    pub merge_rule: MergeRule,
+    /// Row after moving left, indexed by the row before.
+    left: Vec<u16>,
+    /// Row after moving right, indexed by the row before.
+    right: Vec<u16>,
+    /// Points scored by moving a row either way.
+    left_points: Vec<u32>,
+    right_points: Vec<u32>,
+}
+
+impl MoveTables {
+    /// Builds the tables for `merge_rule`.
+    pub fn new(merge_rule: MergeRule) -> MoveTables {
+        let mut tables = MoveTables {
+            merge_rule: merge_rule,
+            left: vec![0; 1 << 16],
+            right: vec![0; 1 << 16],
+            left_points: vec![0; 1 << 16],
+            right_points: vec![0; 1 << 16],
+        };
+        for row in 0..(1 << 16) {
+            let (left, points) = move_row_left(row as u16, merge_rule);
+            tables.left[row] = left;
+            tables.left_points[row] = points;
+            let (right, points) = move_row_left(reverse_row(row as u16), merge_rule);
+            tables.right[row] = reverse_row(right);
+            tables.right_points[row] = points;
+        }
+        tables
+    }
+
+    /// Board after moving `board` in `move_direction`, and the points scored.
+    pub fn apply(&self, board: Bitboard, move_direction: MoveDirection) -> (Bitboard, u32) {
+        match move_direction {
+            MoveDirection::Left => self.apply_rows(board, &self.left, &self.left_points),
+            MoveDirection::Right => self.apply_rows(board, &self.right, &self.right_points),
+            MoveDirection::Up => {
+                let (moved, points) = self.apply_rows(board.transpose(), &self.left, &self.left_points);
+                (moved.transpose(), points)
+            }
+            MoveDirection::Down => {
+                let (moved, points) = self.apply_rows(board.transpose(), &self.right, &self.right_points);
+                (moved.transpose(), points)
+            }
+        }
+    }
+
+    fn apply_rows(&self, board: Bitboard, rows: &[u16], points: &[u32]) -> (Bitboard, u32) {
+        let mut moved = 0;
+        let mut total = 0;
+        for y in 0..BITBOARD_SIZE {
+            let row = ((board.0 >> (16 * y)) & ROW_MASK) as usize;
+            moved |= u64::from(rows[row]) << (16 * y);
+            total += points[row];
+        }
+        (Bitboard(moved), total)
+    }
+
+    /// Whether moving `board` in any direction changes it.
+    pub fn can_move(&self, board: Bitboard) -> bool {
+        DIRECTIONS.iter().any(|&move_direction| self.apply(board, move_direction).0 != board)
+    }
+}
+
+fn reverse_row(row: u16) -> u16 {
+    (row >> 12) | ((row >> 4) & 0x00F0) | ((row << 4) & 0x0F00) | (row << 12)
+}
+
+/// Slides and merges one row towards its lowest cell.
+fn move_row_left(row: u16, merge_rule: MergeRule) -> (u16, u32) {
+    let mut result: Vec<u8> = Vec::with_capacity(BITBOARD_SIZE);
+    let mut merged = [false; BITBOARD_SIZE];
+    let mut points = 0;
+    for x in 0..BITBOARD_SIZE {
+        let exponent = ((row >> (4 * x)) & 0xF) as u8;
+        if exponent == 0 {
+            continue;
+        }
+        let can_merge = match result.last() {
+            Some(&last) => {
+                let allowed = match merge_rule {
+                    MergeRule::OncePerMove => !merged[result.len() - 1],
+                    MergeRule::Cascading => true,
+                };
+                // Two 2^15 tiles would overflow a cell, so they stay apart.
+                last == exponent && exponent < MAX_EXPONENT && allowed
+            }
+            None => false,
+        };
+        if can_merge {
+            let last = result.len() - 1;
+            result[last] += 1;
+            merged[last] = true;
+            points += 1 << result[last];
+        } else {
+            result.push(exponent);
+        }
+    }
+    let packed = result.iter().enumerate()
+        .fold(0, |packed, (x, &exponent)| packed | (u16::from(exponent) << (4 * x)));
+    (packed, points)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitboard::{Bitboard, MoveTables};
+    use {Cell, GameState, Gameboard, MergeRule, MoveDirection, Rules, DIRECTIONS};
+
+    #[test]
+    fn cells_round_trip() {
+        let gameboard: Gameboard = "2|0|0|32768\n0|4|0|0\n8|0|16|0\n0|0|0|1024".parse().unwrap();
+        let board = Bitboard::from_gameboard(&gameboard).unwrap();
+        assert_eq!(board.to_cells(), gameboard.cells);
+        assert_eq!(board.max_tile(), 32768);
+        assert_eq!(board.empty_count(), 10);
+        assert_eq!(board.transpose().transpose(), board);
+        assert_eq!(board.transpose().exponent(3, 0), board.exponent(0, 3));
+    }
+
+    #[test]
+    fn unsupported_boards_are_rejected() {
+        let mut odd = Gameboard::new(false);
+        odd.cells[1][1] = Cell::Occupied(3);
+        let small: Gameboard = "2|0|0\n0|0|0\n0|0|0".parse().unwrap();
+        assert_eq!(Bitboard::from_gameboard(&odd), None);
+        assert_eq!(Bitboard::from_gameboard(&small), None);
+    }
+
+    #[test]
+    fn every_small_row_moves_like_the_engine() {
+        for &merge_rule in [MergeRule::OncePerMove, MergeRule::Cascading].iter() {
+            let tables = MoveTables::new(merge_rule);
+            let mut gameboard = Gameboard::new(false);
+            gameboard.rules.merge_rule = merge_rule;
+            for row in 0..6 * 6 * 6 * 6 {
+                let values: Vec<usize> = (0..4)
+                    .map(|x| match (row / [1, 6, 36, 216][x]) % 6 {
+                        0 => 0,
+                        exponent => 1 << exponent,
+                    })
+                    .collect();
+                let text = format!("{}|{}|{}|{}\n0|0|0|0\n0|0|0|0\n0|0|0|0",
+                                   values[0], values[1], values[2], values[3]);
+                gameboard.cells = text.parse::<Gameboard>().unwrap().cells;
+                let board = Bitboard::from_gameboard(&gameboard).unwrap();
+                for &direction in DIRECTIONS.iter() {
+                    let outcome = gameboard.move_command(direction, gameboard.cells.clone());
+                    let (moved, points) = tables.apply(board, direction);
+                    assert_eq!(moved.to_cells(), outcome.cells, "{} {:?}", text, direction);
+                    assert_eq!(points as usize, outcome.points);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn whole_games_match_the_engine() {
+        let tables = MoveTables::new(MergeRule::OncePerMove);
+        let rules = Rules::new();
+        for seed in 0..20 {
+            let mut gameboard = Gameboard::with_seed(seed, true);
+            let mut rng = ::GameRng::new(seed);
+            let mut board = Bitboard(0);
+            board.spawn(&mut rng, &rules.spawn, rules.spawn.initial_count);
+            let mut score = 0;
+            for i in 0..2000 {
+                assert_eq!(board.to_cells(), gameboard.cells);
+                let direction = DIRECTIONS[(i * 7 + seed as usize) % 4];
+                let (moved, points) = tables.apply(board, direction);
+                if moved != board {
+                    board = moved;
+                    score += points as usize;
+                    board.spawn(&mut rng, &rules.spawn, rules.spawn.per_move_count);
+                }
+                let game_state = gameboard.handle_move(direction);
+                assert_eq!(score, gameboard.score);
+                assert_eq!(tables.can_move(board), game_state != GameState::Lost);
+                if game_state == GameState::Lost {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn largest_tiles_stay_apart() {
+        let text = "32768|32768|0|0\n0|0|0|0\n0|0|0|0\n0|0|0|0";
+        let gameboard: Gameboard = text.parse().unwrap();
+        let board = Bitboard::from_gameboard(&gameboard).unwrap();
+        let (moved, points) = MoveTables::new(MergeRule::OncePerMove).apply(board, MoveDirection::Left);
+        assert_eq!((moved, points), (board, 0));
+        let outcome = gameboard.move_command(MoveDirection::Left, gameboard.cells.clone());
+        assert_eq!(outcome.cells[0][0], Cell::Occupied(65536));
+        assert_eq!(Bitboard::from_cells(&outcome.cells), None);
+    }
+
+    #[test]
+    fn moves_left_and_right() {
+        let tables = MoveTables::new(MergeRule::OncePerMove);
+        let board = Bitboard::from_cells(&"2|2|4|4\n0|0|0|0\n0|0|0|0\n0|0|0|0".parse::<Gameboard>().unwrap().cells).unwrap();
+        let (left, points) = tables.apply(board, MoveDirection::Left);
+        assert_eq!(left.to_cells(), "4|8|0|0\n0|0|0|0\n0|0|0|0\n0|0|0|0".parse::<Gameboard>().unwrap().cells);
+        assert_eq!(points, 12);
+        let (right, _) = tables.apply(board, MoveDirection::Right);
+        assert_eq!(right.to_cells(), "0|0|4|8\n0|0|0|0\n0|0|0|0\n0|0|0|0".parse::<Gameboard>().unwrap().cells);
+    }
+}
@@ -0,0 +1,359 @@
+//! Reinforcement learning environment.
+
+use {Cell, GameRng, GameState, Gameboard, MoveDirection, Rules, DIRECTIONS};
+
+/// Moves in the order actions are numbered.
+pub const ACTIONS: [MoveDirection; 4] = DIRECTIONS;
+
+/// How a board is turned into numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// One value per cell: the exponent of its tile, 0 when empty.
+    Exponents,
+    /// `channels` values per cell, with a 1 at the tile's exponent and 0
+    /// elsewhere. Exponents past the last channel use the last one. Needs
+    /// at least one channel.
+    OneHot { channels: usize },
+}
+
+impl Encoding {
+    /// Number of values in an observation of a `width` by `height` board.
+    pub fn len(&self, width: usize, height: usize) -> usize {
+        match *self {
+            Encoding::Exponents => width * height,
+            Encoding::OneHot { channels } => width * height * channels,
+        }
+    }
+
+    /// Whether observations can be encoded this way.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Encoding::Exponents => true,
+            Encoding::OneHot { channels } => channels > 0,
+        }
+    }
+
+    /// Encodes `gameboard`, cell (x, y) coming at index `y * width + x`.
+    ///
+    /// Panics if the encoding is not valid.
+    pub fn encode(&self, gameboard: &Gameboard) -> Vec<f32> {
+        assert!(self.is_valid(), "one-hot encoding needs at least one channel");
+        let width = gameboard.width();
+        let mut observation = vec![0.0; self.len(width, gameboard.height())];
+        for (x, column) in gameboard.cells.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                let exponent = match *cell {
+                    Cell::Occupied(n) => (n as f64).log2().round() as usize,
+                    Cell::Empty => 0,
+                };
+                let index = y * width + x;
+                match *self {
+                    Encoding::Exponents => observation[index] = exponent as f32,
+                    Encoding::OneHot { channels } => {
+                        observation[index * channels + ::std::cmp::min(exponent, channels - 1)] = 1.0;
+                    }
+                }
+            }
+        }
+        observation
+    }
+}
+
+/// Weights of the terms that make up a step's reward.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RewardShaping {
+    /// Points scored by merges.
+    pub merge_points: f64,
+    /// log2(1 + points scored by merges).
+    pub log_merge_points: f64,
+    /// Given for every step that doesn't lose the game.
+    pub survival: f64,
+    /// Given for an action that doesn't change the board.
+    pub invalid_move: f64,
+    /// Given for the step that loses the game.
+    pub loss: f64,
+}
+
+impl RewardShaping {
+    /// Creates a reward equal to the points scored.
+    pub fn new() -> RewardShaping {
+        RewardShaping {
+            merge_points: 1.0,
+            log_merge_points: 0.0,
+            survival: 0.0,
+            invalid_move: 0.0,
+            loss: 0.0,
+        }
+    }
+}
+
+impl Default for RewardShaping {
+    fn default() -> RewardShaping {
+        RewardShaping::new()
+    }
+}
+
+/// Details of a step that aren't part of the observation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepInfo {
+    pub score: usize,
+    pub moves: usize,
+    pub max_tile: usize,
+    /// Whether the action changed the board.
+    pub moved: bool,
+    pub game_state: GameState,
+    /// Whether the episode ended because it reached `max_steps`.
+    pub truncated: bool,
+}
+
+/// A single game that is played one action at a time.
+pub struct Env {
+    pub rules: Rules,
+    encoding: Encoding,
+    pub reward: RewardShaping,
+    /// Steps after which an episode ends even if the game goes on.
+    pub max_steps: Option<usize>,
+    gameboard: Gameboard,
+    steps: usize,
+}
+
+impl Env {
+    /// Creates an environment over `rules`, observing exponents and
+    /// rewarding points. Call `reset` to start an episode.
+    pub fn new(rules: Rules) -> Env {
+        let gameboard = Gameboard::with_rng(rules.clone(), GameRng::new(0), true);
+        Env {
+            rules: rules,
+            encoding: Encoding::Exponents,
+            reward: RewardShaping::new(),
+            max_steps: None,
+            gameboard: gameboard,
+            steps: 0,
+        }
+    }
+
+    /// Starts a new game from `seed` and returns its first observation.
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.gameboard = Gameboard::with_rng(self.rules.clone(), GameRng::new(seed), true);
+        self.steps = 0;
+        self.observe()
+    }
+
+    /// Number of values in an observation.
+    pub fn observation_len(&self) -> usize {
+        self.encoding.len(self.rules.width, self.rules.height)
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Observes boards through `encoding` from now on.
+    ///
+    /// Panics if the encoding is not valid.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        assert!(encoding.is_valid(), "one-hot encoding needs at least one channel");
+        self.encoding = encoding;
+    }
+
+    pub fn observe(&self) -> Vec<f32> {
+        self.encoding.encode(&self.gameboard)
+    }
+
+    pub fn gameboard(&self) -> &Gameboard {
+        &self.gameboard
+    }
+
+    /// Which actions change the board, in the order of `ACTIONS`.
+    pub fn action_mask(&self) -> [bool; 4] {
+        let mut mask = [false; 4];
+        for (legal, &direction) in mask.iter_mut().zip(ACTIONS.iter()) {
+            *legal = self.gameboard.is_legal(direction);
+        }
+        mask
+    }
+
+    /// Plays `ACTIONS[action]` and returns the observation, the reward,
+    /// whether the episode is over, and details of the step.
+    ///
+    /// Panics if `action` is not below 4.
+    pub fn step(&mut self, action: usize) -> (Vec<f32>, f64, bool, StepInfo) {
+        let score_before = self.gameboard.score;
+        let moves_before = self.gameboard.moves;
+        let mut game_state = self.gameboard.handle_move(ACTIONS[action]);
+        if game_state == GameState::Won {
+            game_state = self.gameboard.state_after_win();
+        }
+        self.steps += 1;
+
+        let moved = self.gameboard.moves > moves_before;
+        let points = (self.gameboard.score - score_before) as f64;
+        let mut reward = self.reward.merge_points * points +
+            self.reward.log_merge_points * (1.0 + points).log2();
+        if !moved {
+            reward += self.reward.invalid_move;
+        }
+        if game_state == GameState::Lost {
+            reward += self.reward.loss;
+        } else {
+            reward += self.reward.survival;
+        }
+
+        let finished = game_state != GameState::Playing;
+        let truncated = !finished && self.max_steps.is_some_and(|limit| self.steps >= limit);
+        let info = StepInfo {
+            score: self.gameboard.score,
+            moves: self.gameboard.moves,
+            max_tile: self.gameboard.max_tile(),
+            moved: moved,
+            game_state: game_state,
+            truncated: truncated,
+        };
+        (self.observe(), reward, finished || truncated, info)
+    }
+}
+
+/// Many environments stepped together.
+///
+/// Observations of all environments are returned one after the other in a
+/// single `Vec`. An environment whose episode ends is reset right away
+/// with the next unused seed, so it always returns the first observation
+/// of its new episode; the step's info still describes the finished one.
+pub struct VecEnv {
+    pub envs: Vec<Env>,
+    next_seed: u64,
+}
+
+impl VecEnv {
+    /// Creates `count` environments over `rules`.
+    pub fn new(count: usize, rules: Rules) -> VecEnv {
+        VecEnv {
+            envs: (0..count).map(|_| Env::new(rules.clone())).collect(),
+            next_seed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    /// Starts environment `i` from `seed + i` and returns all observations.
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        let mut observations = Vec::new();
+        for (i, env) in self.envs.iter_mut().enumerate() {
+            observations.extend(env.reset(seed + i as u64));
+        }
+        self.next_seed = seed + self.envs.len() as u64;
+        observations
+    }
+
+    /// Action masks of all environments.
+    pub fn action_masks(&self) -> Vec<[bool; 4]> {
+        self.envs.iter().map(Env::action_mask).collect()
+    }
+
+    /// Plays `actions[i]` in environment `i`.
+    ///
+    /// Panics unless there is one action per environment.
+    pub fn step(&mut self, actions: &[usize]) -> (Vec<f32>, Vec<f64>, Vec<bool>, Vec<StepInfo>) {
+        assert_eq!(actions.len(), self.envs.len(), "need one action per environment");
+        let mut observations = Vec::new();
+        let mut rewards = Vec::with_capacity(actions.len());
+        let mut dones = Vec::with_capacity(actions.len());
+        let mut infos = Vec::with_capacity(actions.len());
+        for (env, &action) in self.envs.iter_mut().zip(actions.iter()) {
+            let (mut observation, reward, done, info) = env.step(action);
+            if done {
+                observation = env.reset(self.next_seed);
+                self.next_seed += 1;
+            }
+            observations.extend(observation);
+            rewards.push(reward);
+            dones.push(done);
+            infos.push(info);
+        }
+        (observations, rewards, dones, infos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use env::{Encoding, Env, RewardShaping, VecEnv, ACTIONS};
+    use {GameState, Gameboard, MoveDirection, Rules};
+
+    #[test]
+    fn reset_with_a_seed_replays_the_same_game() {
+        let mut env = Env::new(Rules::new());
+        let first = env.reset(9);
+        let mut gameboard = Gameboard::with_seed(9, true);
+        assert_eq!(first, Encoding::Exponents.encode(&gameboard));
+        for action in [0, 3, 2, 1, 3, 3].iter().cloned() {
+            let (observation, reward, _, info) = env.step(action);
+            let score_before = gameboard.score;
+            gameboard.handle_move(ACTIONS[action]);
+            assert_eq!(observation, Encoding::Exponents.encode(&gameboard));
+            assert_eq!(reward, (gameboard.score - score_before) as f64);
+            assert_eq!(info.score, gameboard.score);
+        }
+    }
+
+    #[test]
+    fn one_hot_marks_each_exponent() {
+        let gameboard: Gameboard = "2|0|0\n0|8|0\n0|0|1024".parse().unwrap();
+        let observation = Encoding::OneHot { channels: 4 }.encode(&gameboard);
+        assert_eq!(observation.len(), 36);
+        assert_eq!(&observation[0..4], &[0.0, 1.0, 0.0, 0.0]);
+        assert_eq!(&observation[4..8], &[1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(&observation[16..20], &[0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(&observation[32..36], &[0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(observation.iter().sum::<f32>(), 9.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one channel")]
+    fn one_hot_needs_a_channel() {
+        let mut env = Env::new(Rules::new());
+        env.set_encoding(Encoding::OneHot { channels: 0 });
+    }
+
+    #[test]
+    fn mask_and_shaping_handle_invalid_moves() {
+        let mut env = Env::new(Rules::new());
+        env.reward = RewardShaping {
+            merge_points: 0.0,
+            log_merge_points: 0.0,
+            survival: 1.0,
+            invalid_move: -5.0,
+            loss: -100.0,
+        };
+        env.gameboard = "2|4|8|16\n0|0|0|0\n0|0|0|0\n0|0|0|0".parse().unwrap();
+        let mask = env.action_mask();
+        for (&legal, &direction) in mask.iter().zip(ACTIONS.iter()) {
+            assert_eq!(legal, direction == MoveDirection::Down);
+        }
+        let up = ACTIONS.iter().position(|&direction| direction == MoveDirection::Up).unwrap();
+        assert!(!mask[up]);
+        let (_, reward, done, info) = env.step(up);
+        assert!(!info.moved && !done);
+        assert_eq!(reward, -4.0);
+    }
+
+    #[test]
+    fn vec_env_resets_finished_games() {
+        let mut envs = VecEnv::new(3, Rules::new());
+        envs.envs[1].max_steps = Some(2);
+        let observations = envs.reset(100);
+        assert_eq!(observations.len(), 3 * 16);
+        envs.step(&[0, 1, 2]);
+        let (observations, _, dones, infos) = envs.step(&[3, 3, 3]);
+        assert_eq!(dones, vec![false, true, false]);
+        assert!(infos[1].truncated);
+        assert_eq!(infos[1].game_state, GameState::Playing);
+        let mut fresh = Env::new(Rules::new());
+        assert_eq!(&observations[16..32], &fresh.reset(103)[..]);
+    }
+}
@@ -1,25 +1,45 @@
 
-use rand::{Rng, thread_rng};
+use std::convert::TryFrom;
+use std::error::Error;
 use std::fmt::{self};
+use std::str::FromStr;
 
+use {GameRng, Rules, MergeRule, SpawnCount, WinPolicy};
 
-/// Size of game board.
+
+/// Default size of game board.
 pub const SIZE: usize = 4;
-const NUMBER_OF_FILLED_CELL_AT_START: usize = 4;
-const STARTING_CELL_NUMBER: usize = 2;
-const MAX_NUMBER_OF_NEW_CELLS_TO_ADD: usize = 2;
-const NUMBER_OF_CELLS: usize = SIZE * SIZE;
+/// Smallest supported width or height.
+pub const MIN_SIZE: usize = 3;
+/// Largest supported width or height.
+pub const MAX_SIZE: usize = 8;
+
+/// Whether boards of `width` by `height` cells can be played.
+pub fn is_supported_size(width: usize, height: usize) -> bool {
+    (MIN_SIZE..=MAX_SIZE).contains(&width) && (MIN_SIZE..=MAX_SIZE).contains(&height)
+}
 
-type Cells = [[Cell; SIZE]; SIZE];
+/// Cells indexed by column, then row.
+pub type Cells = Vec<Vec<Cell>>;
 
 /// Stores game board information.
+#[derive(Clone)]
 pub struct Gameboard {
     /// Stores the content of the cells.
     pub cells: Cells,
     pub has_already_won: bool,
+    /// Sum of the values of all tiles merged so far.
+    pub score: usize,
+    /// Number of moves that changed the board.
+    pub moves: usize,
+    /// Milestone tiles reached so far, in the order they were reached.
+    pub milestones_reached: Vec<usize>,
+    pub rules: Rules,
+    /// Generator used for spawning new cells.
+    pub rng: GameRng,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Cell {
     Occupied(usize),
     Empty,
@@ -34,26 +54,150 @@ impl fmt::Display for Cell {
     }
 }
 
+/// Formats the board as rows of `|` separated values with 0 for empty cells,
+/// the same layout `FromStr` accepts.
 impl fmt::Display for Gameboard {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut formatted_str = "".to_string();
-        for i in 0..4{
+        for i in 0..self.height(){
             let mut row : String = "".to_string();
-            for j in 0..4{
+            for j in 0..self.width(){
                 let cell = format!("{}", self.cells[j][i]);
                 row = format!("{}{}", row, cell);
-                if j != 3{
+                if j != self.width() - 1{
                     row = format!("{}{}", row, "|");
                 }
             }
-            formatted_str = format!("{}{}{}", formatted_str, "\n", row);
+            if i != 0{
+                formatted_str = format!("{}{}", formatted_str, "\n");
+            }
+            formatted_str = format!("{}{}", formatted_str, row);
         }
         write!(f, "{}", formatted_str)
     }
 
 }
 
-#[derive(Clone, Copy)]
+/// What was wrong with a board that failed to parse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseBoardErrorKind {
+    /// The text has no rows.
+    Empty,
+    /// A cell is not a number.
+    InvalidNumber(String),
+    /// A cell is neither 0 nor a power of two from 2 upwards.
+    InvalidTile(usize),
+    /// A row has a different number of cells than the first one.
+    RowLength { expected: usize, found: usize },
+    /// The board is smaller or larger than supported.
+    UnsupportedSize { width: usize, height: usize },
+}
+
+/// Error returned when parsing a board from text fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseBoardError {
+    /// Line of the problem, counted from 1.
+    pub line: usize,
+    /// Character column of the problem, counted from 1.
+    pub column: usize,
+    pub kind: ParseBoardErrorKind,
+}
+
+impl fmt::Display for ParseBoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match self.kind {
+            ParseBoardErrorKind::Empty => write!(f, "board has no rows"),
+            ParseBoardErrorKind::InvalidNumber(ref text) => write!(f, "`{}` is not a number", text),
+            ParseBoardErrorKind::InvalidTile(n) => write!(f, "{} is not a power of two", n),
+            ParseBoardErrorKind::RowLength { expected, found } =>
+                write!(f, "expected {} cells, found {}", expected, found),
+            ParseBoardErrorKind::UnsupportedSize { width, height } =>
+                write!(f, "{}x{} boards are not supported, use {}x{} to {}x{}",
+                    width, height, MIN_SIZE, MIN_SIZE, MAX_SIZE, MAX_SIZE),
+        }
+    }
+}
+
+impl Error for ParseBoardError {}
+
+/// Parses rows of `|` separated values as written by `Display`, with 0 for
+/// empty cells. Blank lines are ignored and the board gets the default rules
+/// with the parsed dimensions.
+impl FromStr for Gameboard {
+    type Err = ParseBoardError;
+
+    fn from_str(board_str: &str) -> Result<Gameboard, ParseBoardError> {
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        let mut last_line = 0;
+        for (line_index, line) in board_str.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            last_line = line_index + 1;
+            let error = |column: usize, kind: ParseBoardErrorKind| ParseBoardError {
+                line: line_index + 1,
+                column: column,
+                kind: kind,
+            };
+            let mut row = Vec::new();
+            let mut column = 1;
+            for field in line.split('|') {
+                let text = field.trim();
+                let text_column = column + field.chars().take_while(|c| c.is_whitespace()).count();
+                let cell = match text.parse::<usize>() {
+                    Ok(0) => Cell::Empty,
+                    Ok(n) if n >= 2 && n.is_power_of_two() => Cell::Occupied(n),
+                    Ok(n) => return Err(error(text_column, ParseBoardErrorKind::InvalidTile(n))),
+                    Err(_) => return Err(error(text_column,
+                        ParseBoardErrorKind::InvalidNumber(text.to_string()))),
+                };
+                row.push(cell);
+                column += field.chars().count() + 1;
+            }
+            if let Some(first) = rows.first() {
+                if row.len() != first.len() {
+                    return Err(error(line.chars().count() + 1, ParseBoardErrorKind::RowLength {
+                        expected: first.len(),
+                        found: row.len(),
+                    }));
+                }
+            }
+            rows.push(row);
+        }
+        if rows.is_empty() {
+            return Err(ParseBoardError { line: 1, column: 1, kind: ParseBoardErrorKind::Empty });
+        }
+        let (width, height) = (rows[0].len(), rows.len());
+        if !is_supported_size(width, height) {
+            return Err(ParseBoardError {
+                line: last_line,
+                column: 1,
+                kind: ParseBoardErrorKind::UnsupportedSize { width: width, height: height },
+            });
+        }
+        let mut rules = Rules::new();
+        rules.width = width;
+        rules.height = height;
+        let mut gameboard = Gameboard::with_rules(rules, false);
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, cell) in row.into_iter().enumerate() {
+                gameboard.cells[x][y] = cell;
+            }
+        }
+        Ok(gameboard)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Gameboard {
+    type Error = ParseBoardError;
+
+    fn try_from(board_str: &'a str) -> Result<Gameboard, ParseBoardError> {
+        board_str.parse()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MoveDirection {
     Up,
     Right,
@@ -61,17 +205,24 @@ pub enum MoveDirection {
     Left,
 }
 
-#[derive(Clone, Copy)]
+/// Every direction, in the order moves are listed.
+pub const DIRECTIONS: [MoveDirection; 4] =
+    [MoveDirection::Up, MoveDirection::Right, MoveDirection::Down, MoveDirection::Left];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameState {
     Won,
     Lost,
     Playing,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Location of a cell on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Position {
-    x: usize,
-    y: usize,
+    /// Column index.
+    pub x: usize,
+    /// Row index.
+    pub y: usize,
 }
 
 impl Position{
@@ -83,13 +234,92 @@ impl Position{
     }
 }
 
+/// A tile that slid to another cell without merging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Slide {
+    pub from: Position,
+    pub to: Position,
+    /// Value of the sliding tile.
+    pub value: usize,
+}
+
+/// Two tiles that merged into one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Merge {
+    /// Where the two tiles were before the move.
+    pub sources: [Position; 2],
+    pub target: Position,
+    /// Value of the resulting tile.
+    pub value: usize,
+}
+
+/// Per-tile description of what a single move did.
+#[derive(Clone, Debug)]
+pub struct MoveOutcome {
+    /// Board after the move, before any new cells are added.
+    pub cells: Cells,
+    /// Whether any tile slid or merged.
+    pub moved: bool,
+    pub slides: Vec<Slide>,
+    pub merges: Vec<Merge>,
+    /// Sum of the values of all merged tiles.
+    pub points: usize,
+}
+
+impl MoveOutcome {
+    /// Creates an outcome for a move that has not changed anything yet.
+    pub fn new(cells: Cells) -> MoveOutcome {
+        MoveOutcome {
+            cells: cells,
+            moved: false,
+            slides: Vec::new(),
+            merges: Vec::new(),
+            points: 0,
+        }
+    }
+
+    /// Whether a merge has already produced the tile at `position`.
+    pub fn was_merged(&self, position: Position) -> bool {
+        self.merges.iter().any(|merge| merge.target == position)
+    }
+
+    /// Removes the slide that ended at `position` and returns where it started.
+    fn take_slide_to(&mut self, position: Position) -> Option<Position> {
+        let index = self.slides.iter().position(|slide| slide.to == position);
+        index.map(|i| self.slides.remove(i).from)
+    }
+}
+
 impl Gameboard {
     /// Creates a new game board.
     pub fn new(set_up_board: bool) -> Gameboard {
-        let board = [[Cell::Empty; SIZE]; SIZE];
+        Gameboard::with_rules(Rules::new(), set_up_board)
+    }
+
+    /// Creates a new game board whose cells are spawned from the given seed.
+    pub fn with_seed(seed: u64, set_up_board: bool) -> Gameboard {
+        Gameboard::with_rng(Rules::new(), GameRng::new(seed), set_up_board)
+    }
+
+    /// Creates a new game board played by the given rules.
+    pub fn with_rules(rules: Rules, set_up_board: bool) -> Gameboard {
+        Gameboard::with_rng(rules, GameRng::from_entropy(), set_up_board)
+    }
+
+    /// Creates a new game board played by the given rules, spawning cells from `rng`.
+    pub fn with_rng(rules: Rules, rng: GameRng, set_up_board: bool) -> Gameboard {
+        assert!(is_supported_size(rules.width, rules.height),
+                "Board must be between {0}x{0} and {1}x{1}, got {2}x{3}",
+                MIN_SIZE, MAX_SIZE, rules.width, rules.height);
+        let board = vec![vec![Cell::Empty; rules.height]; rules.width];
         let mut gameboard = Gameboard {
             cells: board,
             has_already_won: false,
+            score: 0,
+            moves: 0,
+            milestones_reached: Vec::new(),
+            rules: rules,
+            rng: rng,
         };
         if set_up_board{
             gameboard.set_up_board();
@@ -97,117 +327,172 @@ impl Gameboard {
         gameboard
     }
 
-    pub fn from_str(&mut self, board_str: String) {
-        let t  = board_str.split_whitespace().map(|row: &str| row.split("|"));
-        for (i, col) in t.enumerate(){
-            for (j, row) in col.enumerate(){
-                let cell = if row == "0"{
-                    Cell::Empty 
-                } else {
-                    Cell::Occupied(row.parse().unwrap())
-                };
-                self.cells[j][i] = cell;
-            }
-        }
-    }
-
     /// Sets up the initial board.
     pub fn set_up_board(&mut self) {
-        for _ in 0..NUMBER_OF_FILLED_CELL_AT_START{
-            let cell_x: usize = thread_rng().gen_range(0, SIZE);
-            let cell_y: usize = thread_rng().gen_range(0, SIZE);
-            self.cells[cell_x][cell_y] = Cell::Occupied(STARTING_CELL_NUMBER);
-        }
+        let count = self.rules.spawn.initial_count;
+        self.spawn_cells(count);
     }
 
+    /// Adds the cells spawned after a move.
     pub fn maybe_add_new_cells(&mut self){
-        let current_num_of_filled = self.number_of_filled_cels();
-        let num_of_free = NUMBER_OF_CELLS - current_num_of_filled;
-        let max_cells_to_add = thread_rng().gen_range(1, MAX_NUMBER_OF_NEW_CELLS_TO_ADD);
-        if num_of_free != 0{
-            let cells_to_add = ::std::cmp::min(num_of_free, max_cells_to_add);
-            for _ in 0..cells_to_add{
-                loop{
-                    let cell_x: usize = thread_rng().gen_range(0, SIZE);
-                    let cell_y: usize = thread_rng().gen_range(0, SIZE);
-                    match self.cell([cell_x, cell_y]) {
-                        Cell::Empty => {
-                            self.set([cell_x, cell_y], Cell::Occupied(STARTING_CELL_NUMBER));
-                            break;
-                        }
-                        Cell::Occupied(_) => ()
-                    }
-                }
+        let count = self.rules.spawn.per_move_count;
+        self.spawn_cells(count);
+    }
+
+    /// Places new cells on empty positions picked uniformly at random.
+    fn spawn_cells(&mut self, count: SpawnCount) {
+        let cells_to_add = count.sample(&mut self.rng);
+        for _ in 0..cells_to_add{
+            let empty = self.empty_positions();
+            if empty.is_empty(){
+                break;
             }
+            let position = empty[self.rng.gen_range(0, empty.len())];
+            let value = self.rules.spawn.sample_value(&mut self.rng);
+            self.cells[position.x][position.y] = Cell::Occupied(value);
         }
     }
 
-    fn number_of_filled_cels(&self) -> usize {
-        let mut count = 0;
-        for i in 0..4{
-            for j in 0..4{
-                match self.cell([i,j]) {
-                    Cell::Occupied(_) => count += 1,
-                    Cell::Empty => (),
+    /// Positions of all empty cells.
+    pub fn empty_positions(&self) -> Vec<Position> {
+        let mut positions = Vec::new();
+        for x in 0..self.width(){
+            for y in 0..self.height(){
+                if let Cell::Empty = self.cells[x][y] {
+                    positions.push(Position::new(x, y));
                 }
             }
         }
-        count
+        positions
     }
 
     fn can_move(&self) -> bool {
-        let mut can_move = false;
-        for movement in [MoveDirection::Up, MoveDirection::Right, MoveDirection::Down, MoveDirection::Left].iter(){
-            let board = self.cells.clone();
-            let (moved, _) = self.move_command(*movement, board);
-            if moved{
-                 can_move = true;
-                 break;
+        DIRECTIONS.iter().any(|&move_direction| self.is_legal(move_direction))
+    }
+
+    /// Whether moving in `move_direction` changes the board.
+    pub fn is_legal(&self, move_direction: MoveDirection) -> bool {
+        Gameboard::cells_can_move(&self.cells, move_direction)
+    }
+
+    /// Whether moving `cells` in `move_direction` changes them: some tile
+    /// has an empty cell or an equal tile next to it on that side.
+    pub fn cells_can_move(cells: &Cells, move_direction: MoveDirection) -> bool {
+        let (dx, dy): (isize, isize) = match move_direction {
+            MoveDirection::Up => (0, -1),
+            MoveDirection::Right => (1, 0),
+            MoveDirection::Down => (0, 1),
+            MoveDirection::Left => (-1, 0),
+        };
+        let width = cells.len() as isize;
+        for (x, column) in cells.iter().enumerate() {
+            let height = column.len() as isize;
+            for (y, cell) in column.iter().enumerate() {
+                let next_x = x as isize + dx;
+                let next_y = y as isize + dy;
+                if *cell == Cell::Empty || next_x < 0 || next_x >= width || next_y < 0 || next_y >= height {
+                    continue;
+                }
+                let next = cells[next_x as usize][next_y as usize];
+                if next == Cell::Empty || next == *cell {
+                    return true;
+                }
             }
         }
-        return can_move;
+        false
     }
 
-    fn has_won(&self) -> bool {
-        let mut has_won = false;
-        for i in 0..4 {
-            for j in 0..4{
-                match self.cells[i][j]{
-                    Cell::Occupied(2048) => {
-                        has_won = true;
-                        break;
-                    }
-                    _ => (),
+    /// Moves that change the board, in the order of `DIRECTIONS`.
+    pub fn legal_moves(&self) -> Vec<MoveDirection> {
+        DIRECTIONS.iter().cloned().filter(|&move_direction| self.is_legal(move_direction)).collect()
+    }
+
+    /// What moving in `move_direction` would do, before new cells spawn,
+    /// or `None` if the move doesn't change the board.
+    pub fn afterstate(&self, move_direction: MoveDirection) -> Option<MoveOutcome> {
+        if self.is_legal(move_direction) {
+            Some(self.move_command(move_direction, self.cells.clone()))
+        } else {
+            None
+        }
+    }
+
+    /// Afterstates of all legal moves, in the order of `DIRECTIONS`.
+    pub fn afterstates(&self) -> Vec<(MoveDirection, MoveOutcome)> {
+        DIRECTIONS.iter()
+            .filter_map(|&move_direction| self.afterstate(move_direction).map(|outcome| (move_direction, outcome)))
+            .collect()
+    }
+
+    /// Value of the highest tile on the board.
+    pub fn max_tile(&self) -> usize {
+        let mut max_tile = 0;
+        for column in self.cells.iter() {
+            for cell in column.iter() {
+                if let Cell::Occupied(n) = *cell {
+                    max_tile = ::std::cmp::max(max_tile, n);
                 }
             }
         }
-        return has_won;
+        max_tile
+    }
+
+    fn has_won(&self) -> bool {
+        self.max_tile() >= self.rules.win.target
+    }
+
+    /// Records milestones reached for the first time.
+    fn reach_milestones(&mut self) {
+        let max_tile = self.max_tile();
+        let mut reached = Vec::new();
+        for &milestone in self.rules.win.milestones.iter() {
+            if max_tile >= milestone && !self.milestones_reached.contains(&milestone) {
+                reached.push(milestone);
+            }
+        }
+        for &milestone in reached.iter() {
+            self.milestones_reached.push(milestone);
+        }
     }
 
     pub fn handle_move(&mut self, move_direction: MoveDirection) -> GameState{
         let board = self.cells.clone();
-        let (moved, board_after_move) = self.move_command(move_direction, board);
-        self.cells = board_after_move;
-        if moved{
+        let outcome = self.move_command(move_direction, board);
+        self.cells = outcome.cells;
+        if outcome.moved{
+            self.score += outcome.points;
+            self.moves += 1;
             self.maybe_add_new_cells();
         }
+        self.reach_milestones();
         let can_still_move = self.can_move();
         if !can_still_move{
-            println!("You lost!");
             return GameState::Lost;
         }
         if !self.has_already_won && self.has_won(){
-            println!("You won!");
             self.has_already_won = true;
-            return GameState::Won;
+            match self.rules.win.policy {
+                WinPolicy::Stop | WinPolicy::Prompt => {
+                    return GameState::Won;
+                }
+                WinPolicy::ContinueSilently => (),
+            }
         }
         return GameState::Playing;
     }
 
-    pub fn move_command(&self, move_direction: MoveDirection, mut cells: Cells) -> (bool, Cells) {
-        let mut executed_move = false;
-        let mut iter_order_x: Vec<usize> = (0..4).collect();
-        let mut iter_order_y: Vec<usize> = (0..4).collect();
+    /// State the game goes to when the player acts after a win.
+    pub fn state_after_win(&self) -> GameState {
+        match self.rules.win.policy {
+            WinPolicy::Stop => GameState::Won,
+            WinPolicy::ContinueSilently | WinPolicy::Prompt => GameState::Playing,
+        }
+    }
+
+    pub fn move_command(&self, move_direction: MoveDirection, cells: Cells) -> MoveOutcome {
+        let mut outcome = MoveOutcome::new(cells);
+        let mut iter_order_x: Vec<usize> = (0..self.width()).collect();
+        let mut iter_order_y: Vec<usize> = (0..self.height()).collect();
         match move_direction {
             MoveDirection::Right => iter_order_x.reverse(),
             MoveDirection::Down => iter_order_y.reverse(),
@@ -215,7 +500,7 @@ impl Gameboard {
         };
         for x in iter_order_x{
             for y in iter_order_y.clone(){
-                let cell = cells[x][y];
+                let cell = outcome.cells[x][y];
                 match cell {
                     Cell::Occupied(n) => {
                         let mut modifications = Vec::new();
@@ -229,14 +514,14 @@ impl Gameboard {
                             }
                             MoveDirection::Right => {
                                 let mut loop_x = x;
-                                while loop_x < 3{
+                                while loop_x < self.width() - 1{
                                     loop_x += 1;
                                     modifications.push(Position::new(loop_x, y));
                                 }
                             }
                             MoveDirection::Down => {
                                 let mut loop_y = y;
-                                while loop_y < 3{
+                                while loop_y < self.height() - 1{
                                     loop_y += 1;
                                     modifications.push(Position::new(x, loop_y));
                                 }
@@ -249,39 +534,75 @@ impl Gameboard {
                                 }
                             }
                         }
-                        executed_move = self.try_apply_modifications(&mut cells, modifications, n, Position::new(x,y));
+                        self.try_apply_modifications(&mut outcome, modifications, n, Position::new(x,y));
                     }
                     Cell::Empty => (),
                 }
             }
         }
-        (executed_move, cells)
+        outcome
     }
 
-    pub fn try_apply_modifications(&self, cells: &mut Cells, modifications: Vec<Position>, current_cell_n: usize, current_cell_position: Position) -> bool{
+    /// Moves the cell at `current_cell_position` as far as `modifications` allows,
+    /// recording the slide or merge in `outcome`. Returns whether the cell moved.
+    pub fn try_apply_modifications(&self, outcome: &mut MoveOutcome, modifications: Vec<Position>, current_cell_n: usize, current_cell_position: Position) -> bool{
         let mut modification: Option<(Cell, Position)> = Option::None;
         for Position{x, y} in modifications{
-            match cells[x][y] {
+            match outcome.cells[x][y] {
                 Cell::Empty => {
                     modification = Option::Some((Cell::Occupied(current_cell_n), Position::new(x, y)));
                 }
                 Cell::Occupied(m) => {
-                    if current_cell_n == m {
+                    let can_merge = match self.rules.merge_rule {
+                        MergeRule::OncePerMove => !outcome.was_merged(Position::new(x, y)),
+                        MergeRule::Cascading => true,
+                    };
+                    if current_cell_n == m && can_merge {
                         modification = Option::Some((Cell::Occupied(current_cell_n * 2), Position::new(x, y)));
                     }
                     break;
                 }
             }
         }
-        let executed_move = match modification{
+        match modification{
             Option::Some((new_cell, next_position)) => {
-                cells[next_position.x][next_position.y] = new_cell;
-                cells[current_cell_position.x][current_cell_position.y] = Cell::Empty;
+                let is_merge = match outcome.cells[next_position.x][next_position.y] {
+                    Cell::Occupied(_) => true,
+                    Cell::Empty => false,
+                };
+                outcome.cells[next_position.x][next_position.y] = new_cell;
+                outcome.cells[current_cell_position.x][current_cell_position.y] = Cell::Empty;
+                if is_merge {
+                    let merged_value = current_cell_n * 2;
+                    let other_source = outcome.take_slide_to(next_position).unwrap_or(next_position);
+                    outcome.merges.push(Merge {
+                        sources: [other_source, current_cell_position],
+                        target: next_position,
+                        value: merged_value,
+                    });
+                    outcome.points += merged_value;
+                } else {
+                    outcome.slides.push(Slide {
+                        from: current_cell_position,
+                        to: next_position,
+                        value: current_cell_n,
+                    });
+                }
+                outcome.moved = true;
                 true
-        }
+            }
             None => false
-        };
-        executed_move
+        }
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> usize {
+        self.rules.width
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> usize {
+        self.rules.height
     }
 
 	/// Gets the character at cell location.
@@ -300,6 +621,9 @@ impl Gameboard {
 #[cfg(test)]
 mod tests {
     use {Gameboard, Cell, MoveDirection};
+    use {Position, Slide, Rules, MergeRule, SpawnCount, WinPolicy, GameState, GameRng, DIRECTIONS};
+    use {ParseBoardError, ParseBoardErrorKind};
+    use std::convert::TryFrom;
 
     #[test]
     fn simple_addition() {
@@ -449,4 +773,317 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn move_is_reported_when_last_cell_stays() {
+        let mut gameboard: Gameboard = Gameboard::new(false);
+        gameboard.cells[0][1] = Cell::Occupied(2);
+        gameboard.cells[3][0] = Cell::Occupied(4);
+        let outcome = gameboard.move_command(MoveDirection::Up, gameboard.cells.clone());
+        assert!(outcome.moved);
+        assert_eq!(outcome.slides, vec![Slide {
+            from: Position::new(0, 1),
+            to: Position::new(0, 0),
+            value: 2,
+        }]);
+        assert!(outcome.merges.is_empty());
+    }
+
+    #[test]
+    fn merge_reports_original_positions_and_points() {
+        let mut gameboard: Gameboard = Gameboard::new(false);
+        gameboard.cells[1][0] = Cell::Occupied(8);
+        gameboard.cells[3][0] = Cell::Occupied(8);
+        let outcome = gameboard.move_command(MoveDirection::Left, gameboard.cells.clone());
+        assert!(outcome.moved);
+        assert!(outcome.slides.is_empty());
+        assert_eq!(outcome.merges.len(), 1);
+        assert_eq!(outcome.merges[0].sources, [Position::new(1, 0), Position::new(3, 0)]);
+        assert_eq!(outcome.merges[0].target, Position::new(0, 0));
+        assert_eq!(outcome.merges[0].value, 16);
+        assert_eq!(outcome.points, 16);
+    }
+
+    #[test]
+    fn blocked_move_is_not_reported() {
+        let mut gameboard: Gameboard = Gameboard::new(false);
+        gameboard.cells[0][0] = Cell::Occupied(2);
+        gameboard.cells[0][1] = Cell::Occupied(4);
+        let outcome = gameboard.move_command(MoveDirection::Up, gameboard.cells.clone());
+        assert!(!outcome.moved);
+        assert_eq!(outcome.points, 0);
+    }
+
+    fn set_row(gameboard: &mut Gameboard, values: [usize; 4]) {
+        for (x, &value) in values.iter().enumerate() {
+            gameboard.cells[x][0] = if value == 0 { Cell::Empty } else { Cell::Occupied(value) };
+        }
+    }
+
+    fn row_values(gameboard: &Gameboard) -> Vec<usize> {
+        (0..4).map(|x| match gameboard.cells[x][0] {
+            Cell::Occupied(n) => n,
+            Cell::Empty => 0,
+        }).collect()
+    }
+
+    #[test]
+    fn merged_tile_does_not_merge_again() {
+        let mut gameboard: Gameboard = Gameboard::new(false);
+        set_row(&mut gameboard, [2, 2, 4, 8]);
+        let outcome = gameboard.move_command(MoveDirection::Left, gameboard.cells.clone());
+        gameboard.cells = outcome.cells;
+        assert_eq!(row_values(&gameboard), vec![4, 4, 8, 0]);
+        assert_eq!(outcome.points, 4);
+    }
+
+    #[test]
+    fn two_pairs_merge_separately() {
+        let mut gameboard: Gameboard = Gameboard::new(false);
+        set_row(&mut gameboard, [2, 2, 2, 2]);
+        let outcome = gameboard.move_command(MoveDirection::Right, gameboard.cells.clone());
+        gameboard.cells = outcome.cells;
+        assert_eq!(row_values(&gameboard), vec![0, 0, 4, 4]);
+        assert_eq!(outcome.merges.len(), 2);
+    }
+
+    #[test]
+    fn cascading_rule_merges_repeatedly() {
+        let mut rules = Rules::new();
+        rules.merge_rule = MergeRule::Cascading;
+        let mut gameboard: Gameboard = Gameboard::with_rules(rules, false);
+        set_row(&mut gameboard, [2, 2, 4, 8]);
+        let outcome = gameboard.move_command(MoveDirection::Left, gameboard.cells.clone());
+        gameboard.cells = outcome.cells;
+        assert_eq!(row_values(&gameboard), vec![16, 0, 0, 0]);
+    }
+
+    #[test]
+    fn same_seed_and_moves_give_same_game() {
+        let moves = [MoveDirection::Up, MoveDirection::Left, MoveDirection::Down, MoveDirection::Right];
+        let mut first = Gameboard::with_seed(2048, true);
+        let mut second = Gameboard::with_seed(2048, true);
+        assert_eq!(first.cells, second.cells);
+        for &movement in moves.iter().cycle().take(40) {
+            first.handle_move(movement);
+            second.handle_move(movement);
+            assert_eq!(first.cells, second.cells);
+        }
+    }
+
+    #[test]
+    fn classic_rules_start_with_two_cells() {
+        let gameboard = Gameboard::with_seed(1, true);
+        assert_eq!(gameboard.empty_positions().len(), 14);
+    }
+
+    #[test]
+    fn spawns_follow_configured_rules() {
+        let mut rules = Rules::new();
+        rules.spawn.values = vec![(4, 1)];
+        rules.spawn.initial_count = SpawnCount::Exact(5);
+        rules.spawn.per_move_count = SpawnCount::Range(2, 3);
+        let mut gameboard = Gameboard::with_rules(rules, true);
+        assert_eq!(gameboard.empty_positions().len(), 11);
+        gameboard.maybe_add_new_cells();
+        let empty = gameboard.empty_positions().len();
+        assert!(empty == 8 || empty == 9);
+        for x in 0..4 {
+            for y in 0..4 {
+                match gameboard.cells[x][y] {
+                    Cell::Occupied(n) => assert_eq!(n, 4),
+                    Cell::Empty => (),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spawning_stops_when_board_is_full() {
+        let mut rules = Rules::new();
+        rules.spawn.initial_count = SpawnCount::Exact(20);
+        let gameboard = Gameboard::with_rules(rules, true);
+        assert!(gameboard.empty_positions().is_empty());
+    }
+
+    #[test]
+    fn rectangular_board_moves_to_its_edges() {
+        let mut rules = Rules::new();
+        rules.width = 5;
+        rules.height = 3;
+        let mut gameboard = Gameboard::with_rules(rules, false);
+        gameboard.cells[0][0] = Cell::Occupied(2);
+        gameboard.cells[0][2] = Cell::Occupied(2);
+        let outcome = gameboard.move_command(MoveDirection::Right, gameboard.cells.clone());
+        assert_eq!(outcome.cells[4][0], Cell::Occupied(2));
+        assert_eq!(outcome.cells[4][2], Cell::Occupied(2));
+        let outcome = gameboard.move_command(MoveDirection::Down, outcome.cells);
+        assert_eq!(outcome.cells[4][2], Cell::Occupied(4));
+        assert_eq!(format!("{}", gameboard), "2|0|0|0|0\n0|0|0|0|0\n2|0|0|0|0");
+    }
+
+    #[test]
+    #[should_panic]
+    fn oversized_board_is_rejected() {
+        let mut rules = Rules::new();
+        rules.width = 9;
+        Gameboard::with_rules(rules, false);
+    }
+
+    fn board_about_to_win(policy: WinPolicy) -> Gameboard {
+        let mut rules = Rules::new();
+        rules.win.target = 16;
+        rules.win.milestones = vec![16, 32];
+        rules.win.policy = policy;
+        let mut gameboard = Gameboard::with_rules(rules, false);
+        gameboard.cells[0][0] = Cell::Occupied(8);
+        gameboard.cells[1][0] = Cell::Occupied(8);
+        gameboard
+    }
+
+    #[test]
+    fn reaching_target_wins() {
+        let mut gameboard = board_about_to_win(WinPolicy::Prompt);
+        match gameboard.handle_move(MoveDirection::Left) {
+            GameState::Won => (),
+            _ => panic!("Game should be won!"),
+        }
+        assert_eq!(gameboard.milestones_reached, vec![16]);
+        match gameboard.state_after_win() {
+            GameState::Playing => (),
+            _ => panic!("Prompt policy should continue!"),
+        }
+    }
+
+    #[test]
+    fn stop_policy_stays_won() {
+        let mut gameboard = board_about_to_win(WinPolicy::Stop);
+        gameboard.handle_move(MoveDirection::Left);
+        match gameboard.state_after_win() {
+            GameState::Won => (),
+            _ => panic!("Stop policy should end the game!"),
+        }
+    }
+
+    #[test]
+    fn silent_policy_keeps_playing() {
+        let mut gameboard = board_about_to_win(WinPolicy::ContinueSilently);
+        match gameboard.handle_move(MoveDirection::Left) {
+            GameState::Playing => (),
+            _ => panic!("Game should go on!"),
+        }
+        assert!(gameboard.has_already_won);
+        assert_eq!(gameboard.milestones_reached, vec![16]);
+    }
+
+    #[test]
+    fn score_counts_merged_values() {
+        let mut gameboard: Gameboard = Gameboard::new(false);
+        set_row(&mut gameboard, [4, 4, 2, 2]);
+        gameboard.handle_move(MoveDirection::Left);
+        assert_eq!(gameboard.score, 12);
+        assert_eq!(gameboard.moves, 1);
+        assert_eq!(gameboard.max_tile(), 8);
+    }
+
+    #[test]
+    fn blocked_moves_are_not_counted() {
+        let mut gameboard: Gameboard = Gameboard::new(false);
+        set_row(&mut gameboard, [2, 4, 0, 0]);
+        gameboard.handle_move(MoveDirection::Left);
+        assert_eq!(gameboard.moves, 0);
+        assert_eq!(gameboard.score, 0);
+    }
+
+    #[test]
+    fn restored_copy_spawns_the_same_cells() {
+        let mut gameboard = Gameboard::with_seed(99, true);
+        let snapshot = gameboard.clone();
+        gameboard.handle_move(MoveDirection::Left);
+        gameboard.handle_move(MoveDirection::Down);
+        let mut restored = snapshot.clone();
+        restored.handle_move(MoveDirection::Left);
+        restored.handle_move(MoveDirection::Down);
+        assert_eq!(gameboard.cells, restored.cells);
+    }
+
+    #[test]
+    fn parsed_board_round_trips() {
+        let text = "2|0|0|4|0\n0|8|0|0|0\n16|0|2048|0|2";
+        let gameboard: Gameboard = text.parse().unwrap();
+        assert_eq!(gameboard.width(), 5);
+        assert_eq!(gameboard.height(), 3);
+        assert_eq!(gameboard.cells[2][2], Cell::Occupied(2048));
+        assert_eq!(gameboard.to_string(), text);
+        let again = Gameboard::try_from(gameboard.to_string().as_str()).unwrap();
+        assert_eq!(again.cells, gameboard.cells);
+    }
+
+    #[test]
+    fn tile_that_is_not_a_power_of_two_is_rejected() {
+        let result = "2|0|0|0\n0|0|0|0\n0|0|6|0\n0|0|0|0".parse::<Gameboard>();
+        assert_eq!(result.err(), Some(ParseBoardError {
+            line: 3,
+            column: 5,
+            kind: ParseBoardErrorKind::InvalidTile(6),
+        }));
+    }
+
+    #[test]
+    fn typo_is_reported_with_position() {
+        let result = "2|0|0|0\n0|0|0|0\n0|0|0|0\n0| x|0|0".parse::<Gameboard>();
+        assert_eq!(result.err(), Some(ParseBoardError {
+            line: 4,
+            column: 4,
+            kind: ParseBoardErrorKind::InvalidNumber("x".to_string()),
+        }));
+    }
+
+    #[test]
+    fn ragged_and_unsupported_boards_are_rejected() {
+        let ragged = "2|0|0|0\n0|0|0\n0|0|0|0\n0|0|0|0".parse::<Gameboard>();
+        assert_eq!(ragged.err().map(|err| err.kind),
+            Some(ParseBoardErrorKind::RowLength { expected: 4, found: 3 }));
+        let tiny = "2|0\n0|0".parse::<Gameboard>();
+        assert_eq!(tiny.err().map(|err| err.kind),
+            Some(ParseBoardErrorKind::UnsupportedSize { width: 2, height: 2 }));
+        let empty = "\n\n".parse::<Gameboard>();
+        assert_eq!(empty.err().map(|err| err.kind), Some(ParseBoardErrorKind::Empty));
+    }
+
+    #[test]
+    fn legal_moves_match_simulated_moves() {
+        for &merge_rule in [MergeRule::OncePerMove, MergeRule::Cascading].iter() {
+            let mut rules = Rules::new();
+            rules.merge_rule = merge_rule;
+            rules.width = 5;
+            let mut gameboard = Gameboard::with_rng(rules, GameRng::new(21), true);
+            for i in 0..300 {
+                for &movement in DIRECTIONS.iter() {
+                    let simulated = gameboard.move_command(movement, gameboard.cells.clone());
+                    assert_eq!(gameboard.is_legal(movement), simulated.moved);
+                }
+                if gameboard.handle_move(DIRECTIONS[i % 3]) == GameState::Lost {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn afterstates_leave_the_board_alone() {
+        let gameboard: Gameboard = "2|2|0\n0|0|0\n4|0|0".parse().unwrap();
+        let before = gameboard.clone();
+        assert_eq!(gameboard.legal_moves(), vec![MoveDirection::Up, MoveDirection::Right, MoveDirection::Down, MoveDirection::Left]);
+        let afterstates = gameboard.afterstates();
+        assert_eq!(afterstates.len(), 4);
+        let (_, ref left) = afterstates[3];
+        assert_eq!(left.cells[0][0], Cell::Occupied(4));
+        assert_eq!(left.points, 4);
+        assert!(gameboard.cells == before.cells);
+        let full: Gameboard = "2|4|2\n4|2|4\n2|4|2".parse().unwrap();
+        assert!(full.legal_moves().is_empty());
+        assert!(full.afterstate(MoveDirection::Up).is_none());
+    }
+
 }
@@ -1,52 +1,588 @@
 //! Gameboard controller.
 
+#[cfg(feature = "window")]
 use piston::input::GenericEvent;
 
-use {Gameboard, MoveDirection, GameState};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Instant;
 
-/// Handles events for Sudoku game.
+use {Gameboard, MoveDirection, GameState, BestScore, History, Snapshot, Expectimax, Hint};
+use {MoveAnimation, TileSprite};
+use animation::still_sprites;
+use {Replay, ReplayPlayer};
+use ai::INTERACTIVE_TIME_BUDGET;
+use save::{self, SaveError, QUICKSAVE_FILE, REPLAY_FILE};
+
+/// Number of earlier positions kept for undo.
+const HISTORY_CAPACITY: usize = 256;
+/// Seconds between autoplay moves.
+const AUTOPLAY_DELAY: f64 = 0.1;
+/// Commands kept while a move animates; later ones are dropped.
+const MAX_QUEUED_COMMANDS: usize = 8;
+/// Seconds the points of a move stay on screen.
+pub const SCORE_POPUP_DURATION: f64 = 0.8;
+
+/// Points scored by the last move, shown for a moment next to the score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScorePopup {
+    pub points: usize,
+    /// Seconds since the points were scored.
+    pub age: f64,
+}
+
+/// Something the player asked for, independent of the input device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Move(MoveDirection),
+    Undo,
+    Redo,
+    Quicksave,
+    Quickload,
+    SaveReplay,
+    /// Asks the AI for the best move.
+    Hint,
+    ToggleAutoplay,
+    /// Applies the next move of the replay being watched.
+    StepForward,
+    /// Takes back the last move of the replay being watched.
+    StepBack,
+    TogglePlaying,
+    Faster,
+    Slower,
+    JumpToStart,
+    JumpToEnd,
+    /// Types a digit of the move number to jump to.
+    JumpDigit(u32),
+    JumpErase,
+    JumpConfirm,
+}
+
+/// Turns player commands into changes to the game.
 pub struct GameboardController {
     /// Stores the gameboard state.
     pub gameboard: Gameboard,
     pub game_state: GameState,
-
+    /// Milestone tile reached by the last move, if any.
+    pub milestone: Option<usize>,
+    /// Direction of the last move that changed the board.
+    pub last_move: Option<MoveDirection>,
+    /// Highest score across games.
+    pub best_score: BestScore,
+    /// Earlier positions that can be undone and redone.
+    pub history: History<Snapshot>,
+    /// Moves of the current game, if it can be replayed from its seed.
+    pub replay: Option<Replay>,
+    /// Replay being watched instead of playing.
+    pub player: Option<ReplayPlayer>,
+    /// Digits typed to jump to a move during playback.
+    pub jump_input: String,
+    /// Feedback on the last command, such as where the game was saved.
+    pub message: Option<String>,
+    /// Player used for hints and autoplay.
+    pub ai: Expectimax,
+    /// Move suggested for the current board.
+    pub hint: Option<Hint>,
+    /// Whether the AI makes the moves.
+    pub autoplay: bool,
+    /// Seconds each move takes to animate, or 0 to show moves at once.
+    pub animation_duration: f64,
+    /// Move being animated.
+    pub animation: Option<MoveAnimation>,
+    /// Commands that arrived during the animation, oldest first.
+    pub queued: VecDeque<Command>,
+    /// Seconds spent on the current game while it could still be played.
+    pub play_time: f64,
+    pub score_popup: Option<ScorePopup>,
+    autoplay_waited: f64,
+    started: Instant,
+    #[cfg(feature = "window")]
+    ctrl_held: bool,
 }
 
 impl GameboardController {
     /// Creates a new gameboard controller.
     pub fn new(gameboard: Gameboard) -> GameboardController {
+        let undo_budget = gameboard.rules.undo_budget;
+        let replay = Replay::new(&gameboard);
+        let mut ai = Expectimax::new();
+        ai.time_budget = Some(INTERACTIVE_TIME_BUDGET);
         GameboardController {
             gameboard: gameboard,
             game_state: GameState::Playing,
+            milestone: None,
+            last_move: None,
+            best_score: BestScore::new(),
+            history: History::new(HISTORY_CAPACITY, undo_budget),
+            replay: Some(replay),
+            player: None,
+            jump_input: String::new(),
+            message: None,
+            ai: ai,
+            hint: None,
+            autoplay: false,
+            animation_duration: 0.0,
+            animation: None,
+            queued: VecDeque::new(),
+            play_time: 0.0,
+            score_popup: None,
+            autoplay_waited: 0.0,
+            started: Instant::now(),
+            #[cfg(feature = "window")]
+            ctrl_held: false,
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            gameboard: self.gameboard.clone(),
+            game_state: self.game_state,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.gameboard = snapshot.gameboard;
+        self.game_state = snapshot.game_state;
+        self.milestone = None;
+        self.hint = None;
+        self.animation = None;
+        self.score_popup = None;
+    }
+
+    /// Saves the game, its history and its play time to `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        save::save_game(path, &self.snapshot(), &self.history, self.play_time)
+    }
+
+    /// Replaces the game, its history and its play time with the ones saved
+    /// at `path`.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SaveError> {
+        let (snapshot, history, play_time) = save::load_game(path)?;
+        // Keep recording only if the replay so far leads to the loaded board.
+        let replay = self.replay.take().and_then(|mut replay| {
+            replay.moves.truncate(snapshot.gameboard.moves);
+            let (replayed, _) = replay.board_after(replay.moves.len());
+            let reproduces = replay.moves.len() == snapshot.gameboard.moves &&
+                replayed.cells == snapshot.gameboard.cells &&
+                replayed.rng == snapshot.gameboard.rng;
+            if reproduces { Some(replay) } else { None }
+        });
+        self.replay = if snapshot.gameboard.moves == 0 {
+            Some(Replay::new(&snapshot.gameboard))
+        } else {
+            replay
+        };
+        self.restore(snapshot);
+        self.history = history;
+        self.play_time = play_time;
+        Ok(())
+    }
+
+    /// Replay of the moves leading to the current board, if known.
+    pub fn current_replay(&self) -> Option<Replay> {
+        self.replay.as_ref().map(|replay| {
+            let mut replay = replay.clone();
+            replay.moves.truncate(self.gameboard.moves);
+            replay
+        })
+    }
+
+    /// Saves the replay of the current game to `path`.
+    pub fn save_replay<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        match self.current_replay() {
+            Some(replay) => save::save_replay(path, &replay),
+            None => Ok(()),
+        }
+    }
+
+    fn quicksave_replay(&mut self) {
+        if self.replay.is_none() {
+            self.message = Some("This game was loaded from a save and can't be replayed".to_string());
+            return;
+        }
+        self.message = Some(match self.save_replay(REPLAY_FILE) {
+            Ok(()) => format!("Saved replay to {}", REPLAY_FILE),
+            Err(err) => format!("Could not save replay: {}", err),
+        });
+    }
+
+    /// Switches to watching `replay` instead of playing.
+    pub fn start_playback(&mut self, replay: Replay) {
+        self.player = Some(ReplayPlayer::new(replay));
+        self.history.clear();
+        self.sync_playback();
+    }
+
+    /// Whether a replay is being watched instead of played.
+    pub fn is_playback(&self) -> bool {
+        self.player.is_some()
+    }
+
+    /// Shows the board the replay player is at.
+    fn sync_playback(&mut self) {
+        if let Some(ref player) = self.player {
+            self.gameboard = player.gameboard.clone();
+            self.game_state = player.game_state;
+            self.milestone = None;
+        }
+    }
+
+    /// Short text to show below the board, if any.
+    pub fn notice(&self) -> Option<String> {
+        if let Some(milestone) = self.milestone {
+            Some(format!("Reached {}!", milestone))
+        } else if let Some(ref hint) = self.hint {
+            Some(format!("Hint: {:?}", hint.direction))
+        } else if self.message.is_some() {
+            self.message.clone()
+        } else if self.autoplay {
+            Some("Autoplay on (P to stop)".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Whether the game changes without input, so `update` must be called regularly.
+    pub fn needs_updates(&self) -> bool {
+        self.player.is_some() || self.autoplay || self.animation.is_some() || self.score_popup.is_some()
+    }
+
+    /// Tiles to draw, in motion while a move animates.
+    pub fn sprites(&self) -> Vec<TileSprite> {
+        match self.animation {
+            Some(ref animation) => animation.sprites(&self.gameboard.cells),
+            None => still_sprites(&self.gameboard.cells),
+        }
+    }
+
+    /// Carries out a command, or queues it until the current move has
+    /// finished animating.
+    pub fn command(&mut self, command: Command) {
+        if self.animation.is_some() {
+            if self.queued.len() < MAX_QUEUED_COMMANDS {
+                self.queued.push_back(command);
+            }
+            return;
+        }
+        self.execute(command);
+    }
+
+    fn execute(&mut self, command: Command) {
+        self.message = None;
+        if command != Command::Hint {
+            self.hint = None;
+        }
+        if self.player.is_some() {
+            self.playback_command(command);
+            return;
+        }
+        match command {
+            Command::Move(move_direction) => self.move_command(move_direction),
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::Quicksave => self.quicksave(),
+            Command::Quickload => self.quickload(),
+            Command::SaveReplay => self.quicksave_replay(),
+            Command::Hint => self.show_hint(),
+            Command::ToggleAutoplay => {
+                self.autoplay = !self.autoplay;
+                self.autoplay_waited = 0.0;
+            }
+            _ => (),
+        }
+    }
+
+    fn show_hint(&mut self) {
+        self.hint = self.ai.hint(&self.gameboard);
+        if self.hint.is_none() {
+            self.message = Some("No move changes the board".to_string());
+        }
+    }
+
+    fn playback_command(&mut self, command: Command) {
+        if let Some(ref mut player) = self.player {
+            match command {
+                Command::StepForward | Command::Move(MoveDirection::Right) => { player.step_forward(); }
+                Command::StepBack | Command::Move(MoveDirection::Left) => { player.step_back(); }
+                Command::TogglePlaying => player.toggle_playing(),
+                Command::Faster => player.speed = (player.speed * 2.0).min(64.0),
+                Command::Slower => player.speed = (player.speed / 2.0).max(1.0 / 64.0),
+                Command::JumpToStart => player.jump_to(0),
+                Command::JumpToEnd => player.jump_to(usize::MAX),
+                Command::JumpDigit(digit) => self.jump_input.push_str(&digit.to_string()),
+                Command::JumpErase => { self.jump_input.pop(); }
+                Command::JumpConfirm => {
+                    if let Ok(position) = self.jump_input.parse() {
+                        player.jump_to(position);
+                    }
+                    self.jump_input.clear();
+                }
+                _ => (),
+            }
+        }
+        self.sync_playback();
+    }
+
+    /// Advances anything that runs on its own by `dt` seconds.
+    pub fn update(&mut self, dt: f64) {
+        if self.player.is_none() && self.game_state == GameState::Playing {
+            self.play_time += dt;
+        }
+        if let Some(ref mut popup) = self.score_popup {
+            popup.age += dt;
+        }
+        if self.score_popup.is_some_and(|popup| popup.age >= SCORE_POPUP_DURATION) {
+            self.score_popup = None;
+        }
+        if let Some(ref mut animation) = self.animation {
+            animation.update(dt);
+        }
+        if self.animation.as_ref().is_some_and(MoveAnimation::is_finished) {
+            self.animation = None;
+        }
+        while self.animation.is_none() {
+            match self.queued.pop_front() {
+                Some(command) => self.execute(command),
+                None => break,
+            }
+        }
+        if self.animation.is_some() {
+            return;
+        }
+        let changed = match self.player {
+            Some(ref mut player) => player.update(dt),
+            None => false,
+        };
+        if changed {
+            self.sync_playback();
+        }
+        if self.autoplay && self.player.is_none() {
+            self.autoplay_waited += dt;
+            if self.autoplay_waited >= AUTOPLAY_DELAY {
+                self.autoplay_waited = 0.0;
+                self.autoplay_move();
+            }
+        }
+    }
+
+    /// Makes the AI's move, stopping autoplay once the game can't go on.
+    fn autoplay_move(&mut self) {
+        let finished = match self.game_state {
+            GameState::Lost => true,
+            GameState::Won => self.gameboard.state_after_win() == GameState::Won,
+            GameState::Playing => false,
+        };
+        let best_move = if finished { None } else { self.ai.best_move(&self.gameboard) };
+        match best_move {
+            Some(move_direction) => {
+                self.move_command(move_direction);
+                self.hint = None;
+            }
+            None => self.autoplay = false,
+        }
+    }
+
+    fn quicksave(&mut self) {
+        self.message = Some(match self.save(QUICKSAVE_FILE) {
+            Ok(()) => format!("Saved game to {}", QUICKSAVE_FILE),
+            Err(err) => format!("Could not save game: {}", err),
+        });
+    }
+
+    fn quickload(&mut self) {
+        self.message = Some(match self.load(QUICKSAVE_FILE) {
+            Ok(()) => format!("Loaded game from {}", QUICKSAVE_FILE),
+            Err(err) => format!("Could not load game: {}", err),
+        });
+    }
+
+    /// Goes back to the position before the last move.
+    pub fn undo(&mut self) {
+        let current = self.snapshot();
+        if let Some(snapshot) = self.history.undo(&current) {
+            self.restore(snapshot);
+        }
+    }
+
+    /// Replays the last undone move.
+    pub fn redo(&mut self) {
+        let current = self.snapshot();
+        if let Some(snapshot) = self.history.redo(&current) {
+            self.restore(snapshot);
         }
     }
 
     fn move_command(&mut self, move_direction: MoveDirection){
         match self.game_state {
             GameState::Playing => {
+                let before = self.snapshot();
+                let milestones_before = self.gameboard.milestones_reached.len();
+                let outcome = if self.animation_duration > 0.0 {
+                    self.gameboard.afterstate(move_direction)
+                } else {
+                    None
+                };
                 let new_game_state = self.gameboard.handle_move(move_direction);
                 self.game_state = new_game_state;
+                if let Some(outcome) = outcome {
+                    self.animation = Some(MoveAnimation::new(outcome, &self.gameboard.cells, self.animation_duration));
+                }
+                if self.gameboard.moves > before.gameboard.moves {
+                    if let Some(ref mut replay) = self.replay {
+                        let elapsed = self.started.elapsed();
+                        let time_ms = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
+                        replay.record(before.gameboard.moves, move_direction, time_ms);
+                    }
+                    let points = self.gameboard.score - before.gameboard.score;
+                    if points > 0 {
+                        self.score_popup = Some(ScorePopup {
+                            points: points,
+                            age: 0.0,
+                        });
+                    }
+                    self.history.record(before);
+                    self.last_move = Some(move_direction);
+                }
+                self.milestone = if self.gameboard.milestones_reached.len() > milestones_before {
+                    self.gameboard.milestones_reached.last().cloned()
+                } else {
+                    None
+                };
+                if let Err(err) = self.best_score.update(self.gameboard.score) {
+                    self.message = Some(format!("Could not save best score: {}", err));
+                }
             }
             GameState::Won => {
-                self.game_state = GameState::Playing;
+                self.game_state = self.gameboard.state_after_win();
             }
             _ =>(),
         }
     }
 
-    /// Handles events.
+    /// Handles events from the Piston window.
+    #[cfg(feature = "window")]
     pub fn event<E: GenericEvent>(&mut self, e: &E) {
         use piston::input::{Button, Key};
 
-        if let Some(Button::Keyboard(key)) = e.press_args(){
+        if let Some(args) = e.update_args() {
+            self.update(args.dt);
+        }
+        if let Some(Button::Keyboard(key)) = e.release_args(){
             match key {
-                Key::Up | Key::W => self.move_command(MoveDirection::Up),
-                Key::Right | Key::D => self.move_command(MoveDirection::Right),
-                Key::Down | Key::S => self.move_command(MoveDirection::Down),
-                Key::Left | Key::A => self.move_command(MoveDirection::Left),
+                Key::LCtrl | Key::RCtrl => self.ctrl_held = false,
                 _ => (),
             }
         }
+        if let Some(Button::Keyboard(key)) = e.press_args(){
+            let command = if self.ctrl_held {
+                match key {
+                    Key::Z => Some(Command::Undo),
+                    Key::Y => Some(Command::Redo),
+                    _ => None,
+                }
+            } else {
+                match key {
+                    Key::LCtrl | Key::RCtrl => {
+                        self.ctrl_held = true;
+                        None
+                    }
+                    Key::U => Some(Command::Undo),
+                    Key::R => Some(Command::Redo),
+                    Key::F5 => Some(Command::Quicksave),
+                    Key::F6 => Some(Command::SaveReplay),
+                    Key::F9 => Some(Command::Quickload),
+                    Key::H => Some(Command::Hint),
+                    Key::P => Some(Command::ToggleAutoplay),
+                    Key::Up | Key::W => Some(Command::Move(MoveDirection::Up)),
+                    Key::Right | Key::D => Some(Command::Move(MoveDirection::Right)),
+                    Key::Down | Key::S => Some(Command::Move(MoveDirection::Down)),
+                    Key::Left | Key::A => Some(Command::Move(MoveDirection::Left)),
+                    Key::Space => Some(Command::TogglePlaying),
+                    Key::Equals | Key::NumPadPlus => Some(Command::Faster),
+                    Key::Minus | Key::NumPadMinus => Some(Command::Slower),
+                    Key::Home => Some(Command::JumpToStart),
+                    Key::End => Some(Command::JumpToEnd),
+                    Key::D0 | Key::D1 | Key::D2 | Key::D3 | Key::D4 |
+                    Key::D5 | Key::D6 | Key::D7 | Key::D8 | Key::D9 =>
+                        Some(Command::JumpDigit(key as u32 - Key::D0 as u32)),
+                    Key::Backspace => Some(Command::JumpErase),
+                    Key::Return => Some(Command::JumpConfirm),
+                    _ => None,
+                }
+            };
+            if let Some(command) = command {
+                self.command(command);
+            }
+        }
+
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Command, Gameboard, GameboardController, MoveDirection};
+    use gameboard_controller::SCORE_POPUP_DURATION;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn hint_is_cleared_by_the_next_move() {
+        let mut controller = GameboardController::new(Gameboard::with_seed(4, true));
+        controller.command(Command::Hint);
+        let hint = controller.hint.clone().expect("A new game always has a move");
+        assert!(hint.value(hint.direction).is_some());
+        controller.command(Command::Move(hint.direction));
+        assert_eq!(controller.hint, None);
+        assert_eq!(controller.gameboard.moves, 1);
+    }
+
+    #[test]
+    fn autoplay_moves_on_update() {
+        let mut controller = GameboardController::new(Gameboard::with_seed(4, true));
+        controller.ai.depth = 1;
+        controller.command(Command::ToggleAutoplay);
+        assert!(controller.needs_updates());
+        for _ in 0..5 {
+            controller.update(1.0);
+        }
+        assert_eq!(controller.gameboard.moves, 5);
+        assert_eq!(controller.history.undo_states().len(), 5);
+    }
+
+    #[test]
+    fn moves_during_an_animation_wait_their_turn() {
+        let mut controller = GameboardController::new("2|0|0|0\n0|0|0|0\n0|0|0|0\n0|0|0|0".parse().unwrap());
+        controller.animation_duration = 0.1;
+        controller.command(Command::Move(MoveDirection::Right));
+        assert!(controller.animation.is_some());
+        let after_first = controller.gameboard.clone();
+        controller.command(Command::Move(MoveDirection::Down));
+        assert_eq!(controller.gameboard.cells, after_first.cells);
+        assert_eq!(controller.queued.len(), 1);
+
+        controller.update(0.05);
+        assert_eq!(controller.gameboard.moves, 1);
+        controller.update(0.05);
+        assert_eq!(controller.gameboard.moves, 2);
+        assert!(controller.queued.is_empty());
+        controller.update(0.1);
+        assert!(controller.animation.is_none());
+        // A spawned tile may have merged on the second move; let its points fade.
+        controller.update(SCORE_POPUP_DURATION);
+        assert!(!controller.needs_updates());
+    }
+
+    #[test]
+    fn loading_restores_the_play_time() {
+        let path = env::temp_dir().join("piston_2048_play_time_test.2048");
+        let mut controller = GameboardController::new(Gameboard::with_seed(6, true));
+        controller.update(42.0);
+        controller.save(&path).unwrap();
 
+        let mut loaded = GameboardController::new(Gameboard::with_seed(7, true));
+        loaded.update(500.0);
+        loaded.load(&path).unwrap();
+        assert_eq!(loaded.play_time, 42.0);
+        fs::remove_file(&path).unwrap();
     }
 }
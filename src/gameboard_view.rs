@@ -4,58 +4,104 @@ use graphics::types::Color;
 use graphics::{Context, Graphics};
 use graphics::character::CharacterCache;
 use graphics::Text;
-use graphics::{Line, Rectangle, Transformed};
+use graphics::{Ellipse, Line, Polygon, Rectangle, Transformed};
 
-use GameboardController;
-use Cell;
+use {GameboardController, Hint, Layout, Marker, MoveDirection, Theme, TileSprite, DIRECTIONS};
+use {hud_items, SCORE_POPUP_DURATION};
+use tile_marker;
 use GameState;
 
 /// Stores gameboard view settings.
 pub struct GameboardViewSettings {
-    /// Position from left-top corner.
-    pub position: [f64; 2],
-    /// Size of gameboard along horizontal and vertical edge.
-    pub size: f64,
-    /// Background color.
-    pub background_color: Color,
-    /// Border color.
-    pub border_color: Color,
-    /// Edge color around the whole board.
-    pub board_edge_color: Color,
-    /// Edge color between the 3x3 sections.
-    pub section_edge_color: Color,
-    /// Edge color between cells.
-    pub cell_edge_color: Color,
-    /// Cell Color
-    pub cell_color: Color,
-    /// Edge radius around the whole board.
+    /// Colors, corner radii and font.
+    pub theme: Theme,
+    /// Width of the edge around the whole board, for 100 pixel cells.
     pub board_edge_radius: f64,
-    /// Edge radius between the 3x3 sections.
-    pub section_edge_radius: f64,
-    /// Edge radius between cells.
-    pub cell_edge_radius: f64,
-	/// Text color
-	pub text_color: Color,
+    /// Width of the lines between cells, for 100 pixel cells.
+    pub grid_radius: f64,
+    /// Whether the hint also shows the expected value of every move.
+    pub show_hint_values: bool,
+    /// Size of all text relative to the usual size.
+    pub text_scale: f64,
+    /// Whether tiles carry shapes as well as colors.
+    pub tile_markers: bool,
 }
 
 impl GameboardViewSettings {
     /// Creates new gameboard view settings.
     pub fn new() -> GameboardViewSettings {
         GameboardViewSettings {
-            position: [10.0; 2],
-            size: 400.0,
-            background_color: [1.0, 0.89, 0.8, 1.0],
-            border_color: [0.0, 0.0, 0.2, 1.0],
-            board_edge_color: [0.0, 0.0, 0.2, 1.0],
-            section_edge_color: [0.0, 0.0, 0.2, 1.0],
-            cell_edge_color: [0.0, 0.0, 0.2, 1.0],
-            cell_color: [1.0, 1.0, 1.0, 0.70],
+            theme: Theme::classic(),
             board_edge_radius: 3.0,
-            section_edge_radius: 2.0,
-            cell_edge_radius: 1.0,
-			text_color: [0.0, 0.0, 0.1, 1.0],
+            grid_radius: 2.0,
+            show_hint_values: true,
+            text_scale: 1.0,
+            tile_markers: false,
         }
     }
+
+    /// Creates settings with large text and tile shapes.
+    pub fn accessible() -> GameboardViewSettings {
+        GameboardViewSettings {
+            text_scale: 1.5,
+            tile_markers: true,
+            ..GameboardViewSettings::new()
+        }
+    }
+}
+
+impl Default for GameboardViewSettings {
+    fn default() -> GameboardViewSettings {
+        GameboardViewSettings::new()
+    }
+}
+
+/// Screen pixels per window unit, above 1 on HiDPI screens.
+fn pixel_density(c: &Context) -> f64 {
+    match c.viewport {
+        Some(viewport) if viewport.window_size[0] > 0.0 => {
+            viewport.draw_size[0] as f64 / viewport.window_size[0]
+        }
+        _ => 1.0,
+    }
+}
+
+/// Width of `text` in window units.
+fn text_width<C: CharacterCache>(glyphs: &mut C, text: &str, font_size: f64, density: f64) -> f64 {
+    let pixel_size = (font_size * density).round() as u32;
+    match glyphs.width(pixel_size, text) {
+        Ok(width) => width / density,
+        // Roughly the advance of an average glyph.
+        Err(_) => 0.6 * font_size * text.chars().count() as f64,
+    }
+}
+
+/// Draws `text` in the color of `style` centered on `position`, a point on
+/// its baseline. The font is `style.font_size` window units tall, shrunk
+/// until the text is at most `max_width` wide.
+fn draw_text<G: Graphics, C>(text: &str,
+        style: Text,
+        position: [f64; 2],
+        max_width: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G) where C: CharacterCache<Texture = G::Texture> {
+    let density = pixel_density(c);
+    let mut font_size = style.font_size as f64;
+    let mut width = text_width(glyphs, text, font_size, density);
+    if width > max_width && width > 0.0 {
+        font_size *= max_width / width;
+        width = text_width(glyphs, text, font_size, density);
+    }
+    // Glyphs are rasterized at the screen's density, then scaled back to
+    // window units, so text stays sharp on HiDPI screens.
+    let pixel_size = (font_size * density).round() as u32;
+    // A glyph that fails to load is left out; the rest of the frame still draws.
+    let _ = Text::new_color(style.color, pixel_size).draw(text,
+                                        glyphs,
+                                        &c.draw_state,
+                                        c.transform.trans(position[0] - width / 2.0, position[1]).zoom(1.0 / density),
+                                        g);
 }
 
 /// Stores visual information about a gameboard.
@@ -72,50 +118,158 @@ impl GameboardView {
         }
     }
 
-    fn get_cell_color(&self, cell: Cell) -> Color {
-        let color: Color = [1.0, 0.8, 0.6, 1.0];
-        match cell {
-            Cell::Occupied(2) => [1.0, 0.8, 0.6, 1.0],
-            Cell::Occupied(4) => [1.0, 0.6, 0.207, 1.0],
-            Cell::Occupied(8) => [1.0, 0.5, 0.0, 1.0],
-            Cell::Occupied(16) => [1.0, 0.4, 0.0, 1.0],
-            Cell::Occupied(32) => [1.0, 0.3, 0.0, 1.0],
-            Cell::Occupied(64) => [1.0, 0.2, 0.0, 1.0],
-            Cell::Occupied(128) => [0.8, 0.2, 0.2, 1.0],
-            Cell::Occupied(256) => [1.0, 1.0, 0.6, 1.0],
-            Cell::Occupied(512) => [1.0, 0.7560, 0.4, 1.0],
-            Cell::Occupied(1024) => [1.0, 0.6, 0.0, 1.0],
-            Cell::Occupied(2048) => [0.84, 0.48, 0.149, 1.0],
-            _ => color,
+
+    /// Draws a tile scaled around the middle of where it currently is.
+    fn draw_tile<G: Graphics, C>(&self,
+            sprite: &TileSprite,
+            layout: &Layout,
+            glyphs: &mut C,
+            c: &Context,
+            g: &mut G) where C: CharacterCache<Texture = G::Texture> {
+        let ref theme = self.settings.theme;
+        let center = layout.cell_center(sprite.x, sprite.y);
+        let fade = |color: Color| [color[0], color[1], color[2], color[3] * sprite.opacity as f32];
+
+        let tile_rect = layout.tile_rect(center, sprite.scale);
+        Rectangle::new_round(fade(theme.tile_color(sprite.value)), tile_rect[2] * theme.tile_radius)
+            .draw(tile_rect, &c.draw_state, c.transform, g);
+
+        // Long numbers shrink to fit; the baseline sits below the middle by
+        // about half the height of a digit.
+        let font_size = (tile_rect[2] * 0.6 * self.settings.text_scale).min(tile_rect[2] * 0.75);
+        let baseline = [center[0], center[1] + font_size * 0.35];
+        draw_text(&sprite.value.to_string(), Text::new_color(fade(theme.tile_text_color(sprite.value)), font_size as u32),
+                  baseline, tile_rect[2] * 0.85, glyphs, c, g);
+
+        if self.settings.tile_markers {
+            self.draw_marker(sprite.value, tile_rect, fade(theme.tile_text_color(sprite.value)), c, g);
+        }
+    }
+
+    /// Draws the shapes marking tile `n` in a row along the top of its
+    /// rectangle.
+    fn draw_marker<G: Graphics>(&self, n: usize, tile_rect: [f64; 4], color: Color, c: &Context, g: &mut G) {
+        let (marker, count) = tile_marker(n);
+        let size = tile_rect[2] * 0.12;
+        for i in 0..count {
+            let x = tile_rect[0] + size * (0.5 + 1.5 * i as f64);
+            let y = tile_rect[1] + size * 0.5;
+            let (middle, right, bottom) = (x + size / 2.0, x + size, y + size);
+            match marker {
+                Marker::Circle => Ellipse::new(color).draw([x, y, size, size], &c.draw_state, c.transform, g),
+                Marker::Square => Rectangle::new(color).draw([x, y, size, size], &c.draw_state, c.transform, g),
+                Marker::Triangle => Polygon::new(color)
+                    .draw(&[[middle, y], [right, bottom], [x, bottom]], &c.draw_state, c.transform, g),
+                Marker::Diamond => Polygon::new(color)
+                    .draw(&[[middle, y], [right, y + size / 2.0], [middle, bottom], [x, y + size / 2.0]],
+                          &c.draw_state, c.transform, g),
+            }
+        }
+    }
+
+    /// Draws an arrow in the hinted direction over the middle of the board,
+    /// and the value of every legal move next to the edge it moves towards.
+    fn draw_hint<G: Graphics, C>(&self,
+            hint: &Hint,
+            layout: &Layout,
+            glyphs: &mut C,
+            c: &Context,
+            g: &mut G) where C: CharacterCache<Texture = G::Texture> {
+        let ref settings = self.settings;
+        let center = layout.board_center();
+        let board_size = layout.board_size;
+        let length = board_size[0].min(board_size[1]) * 0.3;
+        let unit = |direction: MoveDirection| match direction {
+            MoveDirection::Up => [0.0, -1.0],
+            MoveDirection::Right => [1.0, 0.0],
+            MoveDirection::Down => [0.0, 1.0],
+            MoveDirection::Left => [-1.0, 0.0],
+        };
+        let [dx, dy] = unit(hint.direction);
+        let along = |t: f64, side: f64| [
+            center[0] + dx * length * t - dy * length * side,
+            center[1] + dy * length * t + dx * length * side,
+        ];
+        let tail = along(-1.0, 0.0);
+        let neck = along(0.4, 0.0);
+        Line::new_round(settings.theme.hint_color, length * 0.08)
+            .draw([tail[0], tail[1], neck[0], neck[1]], &c.draw_state, c.transform, g);
+        Polygon::new(settings.theme.hint_color)
+            .draw(&[along(1.0, 0.0), along(0.3, 0.35), along(0.3, -0.35)], &c.draw_state, c.transform, g);
+
+        if !settings.show_hint_values {
+            return;
+        }
+        let font_size = length * 0.15 * settings.text_scale;
+        let inset = length * 0.3;
+        for &direction in DIRECTIONS.iter() {
+            let text = match hint.value(direction) {
+                Some(value) => format!("{:.1}", value),
+                None => "-".to_string(),
+            };
+            let [dx, dy] = unit(direction);
+            // Labels sit just inside the middle of the edge.
+            let label_center = [
+                center[0] + dx * (board_size[0] / 2.0 - inset * 1.5),
+                center[1] + dy * (board_size[1] / 2.0 - inset),
+            ];
+            let baseline = [label_center[0], label_center[1] + font_size * 0.35];
+            draw_text(&text, Text::new_color(settings.theme.hint_color, font_size as u32), baseline, inset * 2.5, glyphs, c, g);
+        }
+    }
+
+    /// Draws the panel of figures above the board, and the points of the
+    /// last move floating up into the score.
+    fn draw_hud<G: Graphics, C>(&self,
+            controller: &GameboardController,
+            layout: &Layout,
+            glyphs: &mut C,
+            c: &Context,
+            g: &mut G) where C: CharacterCache<Texture = G::Texture> {
+        let ref theme = self.settings.theme;
+        let items = hud_items(controller);
+        for (i, item) in items.iter().enumerate() {
+            let [x, y, width, height] = layout.hud_box(i, items.len());
+            Rectangle::new_round(theme.board_color, height * 0.1)
+                .draw([x, y, width, height], &c.draw_state, c.transform, g);
+            let middle = x + width / 2.0;
+            draw_text(item.label, Text::new_color(theme.text_color, (height * 0.22) as u32),
+                      [middle, y + height * 0.32], width * 0.9, glyphs, c, g);
+            draw_text(&item.value, Text::new_color(theme.text_color, (height * 0.42) as u32),
+                      [middle, y + height * 0.82], width * 0.9, glyphs, c, g);
+        }
+
+        // The score comes first in the panel.
+        if let Some(popup) = controller.score_popup {
+            let [x, y, width, height] = layout.hud_box(0, items.len());
+            let t = (popup.age / SCORE_POPUP_DURATION).min(1.0);
+            let mut color = theme.hint_color;
+            color[3] *= 1.0 - t as f32;
+            draw_text(&format!("+{}", popup.points), Text::new_color(color, (height * 0.4) as u32),
+                      [x + width / 2.0, y + height * (1.4 - 0.6 * t)], width, glyphs, c, g);
         }
     }
 
     fn draw_dialog<G: Graphics, C>(&self, 
 			glyphs: &mut C, 
             text: &str,
+            layout: &Layout,
 			c: &Context,
 			g: &mut G) where C: CharacterCache<Texture = G::Texture> {
         let ref settings = self.settings;
-        let notif_rect = [
-            settings.position[0] + 40.0, settings.position[1] + 40.0,
-            settings.size - 80.0, settings.size - 80.0,
-        ];
+        let notif_rect = layout.dialog_rect();
         // Draw board background.
-        Rectangle::new_round(settings.cell_color, 10.0)
+        Rectangle::new_round(settings.theme.dialog_color, 10.0 * layout.scale())
             .draw(notif_rect, &c.draw_state, c.transform, g);
-        let text_pos = [
-            settings.position[0] + 80.0,
-            settings.position[1] + 160.0 + 60.0
+        let font_size = notif_rect[3] * 0.2 * settings.text_scale;
+        let baseline = [
+            notif_rect[0] + notif_rect[2] / 2.0,
+            notif_rect[1] + notif_rect[3] / 2.0 + font_size * 0.35,
         ];
-        Text::new_color(settings.text_color, 60).draw(text,
-                                            glyphs,
-                                            &c.draw_state,
-                                            c.transform.trans(text_pos[0], text_pos[1]),
-                                            g);
-
+        draw_text(text, Text::new_color(settings.theme.text_color, font_size as u32), baseline, notif_rect[2] * 0.9, glyphs, c, g);
     }
 
-    /// Draw gameboard.
+    /// Draw gameboard, laid out to fill the window.
     pub fn draw<G: Graphics, C>(&self, 
 			controller: &GameboardController,
 			glyphs: &mut C, 
@@ -123,87 +277,84 @@ impl GameboardView {
 			g: &mut G) where C: CharacterCache<Texture = G::Texture> {
 
         let ref settings = self.settings;
-        let board_rect = [
-            settings.position[0], settings.position[1],
-            settings.size, settings.size,
-        ];
+        let gameboard = &controller.gameboard;
+        let (width, height) = (gameboard.width(), gameboard.height());
+        let layout = Layout::new(c.get_view_size(), width, height, settings.text_scale);
+        let board_rect = layout.board_rect();
 
         // Draw board background.
-        Rectangle::new(settings.background_color)
+        Rectangle::new(settings.theme.board_color)
             .draw(board_rect, &c.draw_state, c.transform, g);
 
-        // Draw section borders.
-        let section_edge = Line::new(settings.section_edge_color, settings.section_edge_radius);
-		let cell_size = settings.size / 4.0;
-        for i in 0..4 {
-            // Set up coordinates.
-            let x = settings.position[0] + i as f64 / 4.0 * settings.size;
-            let y = settings.position[1] + i as f64 / 4.0 * settings.size;
-            let x2 = settings.position[0] + settings.size;
-            let y2 = settings.position[1] + settings.size;
-
-            let vline = [x, settings.position[1], x, y2];
-            section_edge.draw(vline, &c.draw_state, c.transform, g);
-
-            let hline = [settings.position[0], y, x2, y];
-            section_edge.draw(hline, &c.draw_state, c.transform, g);
+        // Draw lines between cells.
+        let grid_line = Line::new(settings.theme.grid_color, settings.grid_radius * layout.scale());
+        let x2 = layout.position[0] + layout.board_size[0];
+        let y2 = layout.position[1] + layout.board_size[1];
+        for i in 0..width {
+            let x = layout.position[0] + i as f64 * layout.cell_size;
+            let vline = [x, layout.position[1], x, y2];
+            grid_line.draw(vline, &c.draw_state, c.transform, g);
+        }
+        for i in 0..height {
+            let y = layout.position[1] + i as f64 * layout.cell_size;
+            let hline = [layout.position[0], y, x2, y];
+            grid_line.draw(hline, &c.draw_state, c.transform, g);
         }
 
         // Draw board edge.
-        Rectangle::new_round_border(settings.board_edge_color, 5.0, settings.board_edge_radius)
+        Rectangle::new_round_border(settings.theme.board_edge_color, settings.theme.board_radius * layout.scale(), settings.board_edge_radius * layout.scale())
             .draw(board_rect, &c.draw_state, c.transform, g);
 
-		// Draw characters.
-		for i in 0..4 {
-			for j in 0..4 {
-                let cell = controller.gameboard.cell([i, j]);
-                match cell {
-                    Cell::Occupied(n) => {
-                        let cell_color = self.get_cell_color(cell);
-                        let cell_rect = [
-                            settings.position[0] + j as f64 * cell_size + 10.0,
-                            settings.position[1] + i as f64 * cell_size + 10.0,
-                            cell_size - 20.0,
-                            cell_size - 20.0 ,
-                        ];
-                        Rectangle::new_round(cell_color, 5.0).draw(cell_rect, &c.draw_state, c.transform, g);
-                        let cell_val_str: String = n.to_string();
-                        let n_char = cell_val_str.chars().count();
-                        let pad_x = match n_char {
-                            1 => 25.0,
-                            2 => 15.0,
-                            3 => 2.5,
-                            _ => 0.0,
-                        };
-                        let font_size = if n_char <= 3{
-                            50
-                        } else {
-                            40
-                        };
-                        let pos = [
-                            settings.position[0] + j as f64 * cell_size + 10.0 + pad_x,
-                            settings.position[1] + i as f64 * cell_size + 60.0
-                        ];
-                        Text::new_color(settings.text_color, font_size).draw(&n.to_string(),
-                                                            glyphs,
-                                                            &c.draw_state,
-                                                            c.transform.trans(pos[0], pos[1]),
-                                                            g);
-
-                    }
-                    Cell::Empty => (),
-                }
-			}
-
-            match controller.game_state{
-                GameState::Lost => {
-                    self.draw_dialog(glyphs, "You lost!", &c, g);
-                }
-                GameState::Won => {
-                    self.draw_dialog(glyphs, "You won!", &c, g);
-                }
-                _ => (),
+        // Draw tiles, wherever the current animation has them.
+        for sprite in controller.sprites() {
+            self.draw_tile(&sprite, &layout, glyphs, c, g);
+        }
+
+        if let Some(ref hint) = controller.hint {
+            self.draw_hint(hint, &layout, glyphs, c, g);
+        }
+
+        self.draw_hud(controller, &layout, glyphs, c, g);
+
+        // Draw undo and redo hints.
+        let undo_text = match controller.history.undos_remaining() {
+            Some(n) => format!("Undo (U): {} left", n),
+            None => "Undo (U)".to_string(),
+        };
+        let undo_text = match (controller.history.can_undo(), controller.history.can_redo()) {
+            (true, true) => format!("{}  Redo (R)", undo_text),
+            (true, false) => undo_text,
+            (false, true) => "Redo (R)".to_string(),
+            (false, false) => "".to_string(),
+        };
+        // Playback status replaces the undo hints while watching a replay.
+        let undo_text = match controller.player {
+            Some(ref player) => format!("Replay: move {}/{}  x{}  {}{}",
+                player.position, player.len(), player.speed,
+                if player.playing { "playing" } else { "paused" },
+                if controller.jump_input.is_empty() {
+                    "".to_string()
+                } else {
+                    format!("  go to: {}", controller.jump_input)
+                }),
+            None => undo_text,
+        };
+        let (undo_pos, undo_size) = layout.status_line(0);
+        draw_text(&undo_text, Text::new_color(settings.theme.text_color, undo_size as u32), undo_pos, layout.text_width, glyphs, c, g);
+
+        if let Some(notice) = controller.notice() {
+            let (text_pos, text_size) = layout.status_line(1);
+            draw_text(&notice, Text::new_color(settings.theme.text_color, text_size as u32), text_pos, layout.text_width, glyphs, c, g);
+        }
+
+        match controller.game_state{
+            GameState::Lost => {
+                self.draw_dialog(glyphs, "You lost!", &layout, c, g);
+            }
+            GameState::Won => {
+                self.draw_dialog(glyphs, "You won!", &layout, c, g);
             }
-		}
+            _ => (),
+        }
     }
 }
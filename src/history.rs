@@ -0,0 +1,184 @@
+//! Undo and redo history.
+
+use std::collections::VecDeque;
+
+use {Gameboard, GameState, UndoBudget};
+
+/// Game state restored by undo and redo.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub gameboard: Gameboard,
+    pub game_state: GameState,
+}
+
+/// Bounded stack of earlier states that can be undone and redone.
+pub struct History<T> {
+    undo_stack: VecDeque<T>,
+    redo_stack: Vec<T>,
+    capacity: usize,
+    budget: UndoBudget,
+    /// Number of undos used so far.
+    pub undos_used: usize,
+}
+
+impl<T: Clone> History<T> {
+    /// Creates an empty history keeping at most `capacity` states.
+    pub fn new(capacity: usize, budget: UndoBudget) -> History<T> {
+        History {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            capacity: capacity,
+            budget: budget,
+            undos_used: 0,
+        }
+    }
+
+    /// Creates a history from previously saved states, oldest first.
+    pub fn from_parts(capacity: usize, budget: UndoBudget, undo: Vec<T>, redo: Vec<T>, undos_used: usize) -> History<T> {
+        History {
+            undo_stack: undo.into_iter().collect(),
+            redo_stack: redo,
+            capacity: capacity,
+            budget: budget,
+            undos_used: undos_used,
+        }
+    }
+
+    /// Maximum number of states kept for undo.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// States that can be undone, oldest first.
+    pub fn undo_states(&self) -> Vec<&T> {
+        self.undo_stack.iter().collect()
+    }
+
+    /// States that can be redone, in the order they are stored.
+    pub fn redo_states(&self) -> Vec<&T> {
+        self.redo_stack.iter().collect()
+    }
+
+    /// Records the state from before a change, forgetting anything that could be redone.
+    pub fn record(&mut self, state: T) {
+        self.push_undo(state);
+        self.redo_stack.clear();
+    }
+
+    /// Keeps `state` for undo, dropping the oldest state when full.
+    fn push_undo(&mut self, state: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.undo_stack.len() >= self.capacity {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(state);
+    }
+
+    /// Number of undos left, or `None` when unlimited.
+    pub fn undos_remaining(&self) -> Option<usize> {
+        match self.budget {
+            UndoBudget::Unlimited => None,
+            UndoBudget::PerGame(n) => Some(n.saturating_sub(self.undos_used)),
+            UndoBudget::Disabled => Some(0),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() && self.undos_remaining() != Some(0)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Steps back from `current`, returning the state to restore.
+    pub fn undo(&mut self, current: &T) -> Option<T> {
+        if !self.can_undo() {
+            return None;
+        }
+        let state = self.undo_stack.pop_back();
+        self.redo_stack.push(current.clone());
+        self.undos_used += 1;
+        state
+    }
+
+    /// Steps forward from `current` after an undo, returning the state to restore.
+    pub fn redo(&mut self, current: &T) -> Option<T> {
+        let state = self.redo_stack.pop();
+        if state.is_some() {
+            self.push_undo(current.clone());
+        }
+        state
+    }
+
+    /// Forgets all states and resets the undo count.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.undos_used = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {History, UndoBudget};
+
+    #[test]
+    fn undo_and_redo_walk_the_history() {
+        let mut history = History::new(10, UndoBudget::Unlimited);
+        history.record(1);
+        history.record(2);
+        assert_eq!(history.undo(&3), Some(2));
+        assert_eq!(history.undo(&2), Some(1));
+        assert_eq!(history.undo(&1), None);
+        assert_eq!(history.redo(&1), Some(2));
+        assert_eq!(history.redo(&2), Some(3));
+        assert_eq!(history.redo(&3), None);
+    }
+
+    #[test]
+    fn redo_keeps_the_capacity() {
+        let mut history = History::from_parts(2, UndoBudget::Unlimited, vec![1, 2], vec![4], 0);
+        assert_eq!(history.redo(&3), Some(4));
+        assert_eq!(history.undo_states(), vec![&2, &3]);
+
+        let mut history = History::from_parts(0, UndoBudget::Unlimited, Vec::new(), vec![2], 0);
+        assert_eq!(history.redo(&1), Some(2));
+        assert!(history.undo_states().is_empty());
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut history = History::new(10, UndoBudget::Unlimited);
+        history.record(1);
+        history.undo(&2);
+        history.record(1);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn oldest_state_is_dropped_beyond_capacity() {
+        let mut history = History::new(2, UndoBudget::Unlimited);
+        history.record(1);
+        history.record(2);
+        history.record(3);
+        assert_eq!(history.undo(&4), Some(3));
+        assert_eq!(history.undo(&3), Some(2));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn budget_limits_undos() {
+        let mut history = History::new(10, UndoBudget::PerGame(1));
+        history.record(1);
+        history.record(2);
+        assert_eq!(history.undos_remaining(), Some(1));
+        assert_eq!(history.undo(&3), Some(2));
+        assert_eq!(history.undo(&2), None);
+        let mut disabled = History::new(10, UndoBudget::Disabled);
+        disabled.record(1);
+        assert!(!disabled.can_undo());
+    }
+}
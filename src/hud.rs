@@ -0,0 +1,74 @@
+//! Figures shown in the panel above the board.
+
+use GameboardController;
+
+/// One labelled figure of the panel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HudItem {
+    pub label: &'static str,
+    pub value: String,
+}
+
+impl HudItem {
+    fn new(label: &'static str, value: String) -> HudItem {
+        HudItem {
+            label: label,
+            value: value,
+        }
+    }
+}
+
+/// Figures of the game `controller` is showing, score first.
+pub fn hud_items(controller: &GameboardController) -> Vec<HudItem> {
+    let gameboard = &controller.gameboard;
+    let undos = match controller.history.undos_remaining() {
+        Some(n) => n.to_string(),
+        None => "\u{221e}".to_string(),
+    };
+    vec![
+        HudItem::new("SCORE", gameboard.score.to_string()),
+        HudItem::new("BEST", controller.best_score.value.to_string()),
+        HudItem::new("MOVES", gameboard.moves.to_string()),
+        HudItem::new("TIME", format_time(controller.play_time)),
+        HudItem::new("MAX", gameboard.max_tile().to_string()),
+        HudItem::new("UNDOS", undos),
+    ]
+}
+
+/// Formats `seconds` as `m:ss`, or `h:mm:ss` from an hour on.
+pub fn format_time(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hud::{format_time, hud_items};
+    use {Command, Gameboard, GameboardController, MoveDirection, UndoBudget};
+
+    #[test]
+    fn time_is_shown_in_minutes_and_hours() {
+        assert_eq!(format_time(0.4), "0:00");
+        assert_eq!(format_time(75.9), "1:15");
+        assert_eq!(format_time(3723.0), "1:02:03");
+    }
+
+    #[test]
+    fn panel_follows_the_game() {
+        let mut gameboard: Gameboard = "2|2|0\n0|0|0\n0|0|0".parse().unwrap();
+        gameboard.rules.undo_budget = UndoBudget::PerGame(3);
+        let mut controller = GameboardController::new(gameboard);
+        controller.command(Command::Move(MoveDirection::Left));
+        assert_eq!(controller.score_popup.map(|popup| popup.points), Some(4));
+        controller.update(61.0);
+        let items = hud_items(&controller);
+        let values: Vec<&str> = items.iter().map(|item| item.value.as_str()).collect();
+        assert_eq!(values, vec!["4", "4", "1", "1:01", "4", "3"]);
+        assert!(controller.score_popup.is_none());
+    }
+}
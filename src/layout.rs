@@ -0,0 +1,161 @@
+//! Where the board and its text go in a window of any size.
+
+/// Empty space around the board, as a fraction of the window's shorter side.
+const MARGIN: f64 = 0.025;
+/// Height of the panel above the board, as a fraction of the window height.
+const HUD_HEIGHT: f64 = 0.12;
+/// Height of the text below the board, as a fraction of the window height.
+const STATUS_HEIGHT: f64 = 0.14;
+/// Most of the window height the panel and the text below the board may
+/// take together.
+const MAX_TEXT_HEIGHT: f64 = 0.5;
+/// Space between the boxes of the panel, as a fraction of their height.
+const HUD_GAP: f64 = 0.15;
+/// Space between a tile and the edge of its cell, as a fraction of the cell.
+const TILE_MARGIN: f64 = 0.1;
+/// Baselines of the status lines, as fractions of the status height.
+const STATUS_BASELINES: [f64; 2] = [0.4, 0.85];
+/// Font sizes of the status lines, as fractions of the status height.
+const STATUS_FONT_SIZES: [f64; 2] = [0.22, 0.32];
+
+/// Sizes and positions of everything drawn, in window coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    /// Left-top corner of the board.
+    pub position: [f64; 2],
+    /// Side of a square cell.
+    pub cell_size: f64,
+    /// Width and height of the board.
+    pub board_size: [f64; 2],
+    /// Panel above the board.
+    pub hud_rect: [f64; 4],
+    /// Height of the text area below the board.
+    pub status_height: f64,
+    /// Widest a line of text may be.
+    pub text_width: f64,
+    /// Horizontal middle of the window, which text is centered on.
+    pub center_x: f64,
+}
+
+impl Layout {
+    /// Fits the panel, a `width` by `height` board and its status lines
+    /// into a window of `window_size`, one below the other, centering the
+    /// board horizontally. Text is made `text_scale` times its usual size,
+    /// taking room from the board.
+    pub fn new(window_size: [f64; 2], width: usize, height: usize, text_scale: f64) -> Layout {
+        let margin = window_size[0].min(window_size[1]) * MARGIN;
+        let text_share = ((HUD_HEIGHT + STATUS_HEIGHT) * text_scale).min(MAX_TEXT_HEIGHT);
+        let hud_height = window_size[1] * text_share * HUD_HEIGHT / (HUD_HEIGHT + STATUS_HEIGHT);
+        let status_height = window_size[1] * text_share - hud_height;
+        let text_width = (window_size[0] - 2.0 * margin).max(0.0);
+        let available = [
+            text_width,
+            (window_size[1] - 3.0 * margin - hud_height - status_height).max(0.0),
+        ];
+        let cell_size = (available[0] / width as f64).min(available[1] / height as f64);
+        let board_size = [cell_size * width as f64, cell_size * height as f64];
+        Layout {
+            position: [(window_size[0] - board_size[0]) / 2.0, 2.0 * margin + hud_height],
+            cell_size: cell_size,
+            board_size: board_size,
+            hud_rect: [margin, margin, text_width, hud_height],
+            status_height: status_height,
+            text_width: text_width,
+            center_x: window_size[0] / 2.0,
+        }
+    }
+
+    /// Ratio to sizes that were tuned for 100 pixel cells.
+    pub fn scale(&self) -> f64 {
+        self.cell_size / 100.0
+    }
+
+    /// Middle of the board.
+    pub fn board_center(&self) -> [f64; 2] {
+        [self.position[0] + self.board_size[0] / 2.0, self.position[1] + self.board_size[1] / 2.0]
+    }
+
+    /// Rectangle of the whole board.
+    pub fn board_rect(&self) -> [f64; 4] {
+        [self.position[0], self.position[1], self.board_size[0], self.board_size[1]]
+    }
+
+    /// Middle of the cell at column `x` and row `y`, which may be fractional.
+    pub fn cell_center(&self, x: f64, y: f64) -> [f64; 2] {
+        [self.position[0] + (x + 0.5) * self.cell_size, self.position[1] + (y + 0.5) * self.cell_size]
+    }
+
+    /// Rectangle of a tile centered on `center`, grown by `scale`.
+    pub fn tile_rect(&self, center: [f64; 2], scale: f64) -> [f64; 4] {
+        let side = self.cell_size * (1.0 - 2.0 * TILE_MARGIN) * scale;
+        [center[0] - side / 2.0, center[1] - side / 2.0, side, side]
+    }
+
+    /// Middle of the baseline and font size of status line `line`,
+    /// counted from 0.
+    pub fn status_line(&self, line: usize) -> ([f64; 2], f64) {
+        let top = self.position[1] + self.board_size[1];
+        let baseline = [self.center_x, top + STATUS_BASELINES[line] * self.status_height];
+        (baseline, STATUS_FONT_SIZES[line] * self.status_height)
+    }
+
+    /// Box `index` of `count` boxes side by side in the panel.
+    pub fn hud_box(&self, index: usize, count: usize) -> [f64; 4] {
+        let [x, y, width, height] = self.hud_rect;
+        let gap = height * HUD_GAP;
+        let box_width = (width - gap * (count as f64 - 1.0)) / count as f64;
+        [x + index as f64 * (box_width + gap), y, box_width, height]
+    }
+
+    /// Rectangle of the dialog shown over the board.
+    pub fn dialog_rect(&self) -> [f64; 4] {
+        let inset = self.board_size[0].min(self.board_size[1]) * 0.1;
+        [
+            self.position[0] + inset, self.position[1] + inset,
+            self.board_size[0] - 2.0 * inset, self.board_size[1] - 2.0 * inset,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use layout::Layout;
+
+    #[test]
+    fn board_fits_and_is_centered() {
+        let layout = Layout::new([800.0, 500.0], 4, 4, 1.0);
+        assert!(layout.position[1] + layout.board_size[1] + layout.status_height <= 500.0);
+        assert!((layout.position[0] * 2.0 + layout.board_size[0] - 800.0).abs() < 1e-9);
+
+        let wide = Layout::new([500.0, 1000.0], 6, 3, 1.0);
+        assert!(wide.position[0] >= 0.0);
+        assert!(wide.board_size[0] <= 500.0);
+        assert_eq!(wide.board_size[0], wide.cell_size * 6.0);
+    }
+
+    #[test]
+    fn layout_scales_with_the_window() {
+        let small = Layout::new([512.0, 512.0], 4, 4, 1.0);
+        let large = Layout::new([1024.0, 1024.0], 4, 4, 1.0);
+        assert_eq!(large.cell_size, small.cell_size * 2.0);
+        assert_eq!(large.status_line(1).1, small.status_line(1).1 * 2.0);
+        let tile = large.tile_rect(large.cell_center(0.0, 0.0), 1.0);
+        assert!((tile[0] - (large.position[0] + large.cell_size * 0.1)).abs() < 1e-9);
+
+        let large_text = Layout::new([512.0, 512.0], 4, 4, 1.5);
+        assert!((large_text.status_line(0).1 - small.status_line(0).1 * 1.5).abs() < 1e-9);
+        assert!(large_text.cell_size < small.cell_size);
+    }
+
+    #[test]
+    fn panel_sits_above_the_board() {
+        let layout = Layout::new([512.0, 512.0], 4, 4, 1.0);
+        let hud = layout.hud_rect;
+        assert!(hud[1] + hud[3] < layout.position[1]);
+        let first = layout.hud_box(0, 6);
+        let last = layout.hud_box(5, 6);
+        assert_eq!(first[0], hud[0]);
+        assert!((last[0] + last[2] - (hud[0] + hud[2])).abs() < 1e-9);
+        assert!(first[0] + first[2] < layout.hud_box(1, 6)[0]);
+    }
+}
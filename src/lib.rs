@@ -0,0 +1,55 @@
+//! A 2048 game engine.
+//!
+//! The engine has no graphics dependencies. The Piston view and the
+//! window's key handling are only built with the `window` feature.
+#[cfg(feature = "window")]
+extern crate piston;
+#[cfg(feature = "window")]
+extern crate graphics;
+extern crate rand;
+
+pub use accessibility::{tile_marker, Marker, MoveLog};
+pub use ai::{Expectimax, Heuristic, Heuristics, Hint};
+pub use animation::{MoveAnimation, TileSprite, DEFAULT_ANIMATION_DURATION};
+pub use bitboard::{Bitboard, MoveTables, BITBOARD_SIZE, MAX_EXPONENT};
+pub use best_score::{BestScore, BEST_SCORE_FILE};
+pub use env::{Env, VecEnv};
+pub use gameboard::Gameboard;
+pub use gameboard::{Cell, MoveDirection, DIRECTIONS, SIZE, MIN_SIZE, MAX_SIZE, GameState, is_supported_size};
+pub use gameboard::{Cells, Position, MoveOutcome, Slide, Merge, ParseBoardError, ParseBoardErrorKind};
+pub use gameboard_controller::{GameboardController, Command, ScorePopup, SCORE_POPUP_DURATION};
+#[cfg(feature = "window")]
+pub use gameboard_view::{GameboardView, GameboardViewSettings};
+pub use history::{History, Snapshot};
+pub use hud::{hud_items, format_time, HudItem};
+pub use layout::Layout;
+pub use rng::GameRng;
+pub use replay::{Replay, ReplayMove, ReplayPlayer};
+pub use save::{save_game, load_game, save_replay, load_replay};
+pub use save::{SaveError, SAVE_VERSION, QUICKSAVE_FILE, AUTOSAVE_FILE, REPLAY_VERSION, REPLAY_FILE};
+pub use strategy::{Strategy, MonteCarlo};
+pub use theme::{Theme, ThemeError, THEME_VERSION, DEFAULT_FONT};
+pub use rules::{Rules, MergeRule, SpawnRules, SpawnCount, WinRules, WinPolicy, UndoBudget};
+
+mod accessibility;
+pub mod ai;
+mod animation;
+pub mod args;
+mod best_score;
+mod bitboard;
+pub mod env;
+mod gameboard;
+mod gameboard_controller;
+#[cfg(feature = "window")]
+mod gameboard_view;
+mod history;
+mod hud;
+mod layout;
+pub mod palette;
+mod replay;
+mod rng;
+mod rules;
+mod save;
+pub mod strategy;
+mod theme;
+
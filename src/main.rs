@@ -4,54 +4,140 @@ extern crate piston;
 extern crate glutin_window;
 extern crate graphics;
 extern crate opengl_graphics;
-extern crate rand;
+extern crate piston_2048;
+
+use std::fs::File;
+use std::io::{self, Write};
 
 use piston::window::WindowSettings;
 use glutin_window::GlutinWindow;
-use piston::event_loop::{Events, EventSettings, EventLoop};
+use piston::event_loop::{Events, EventSettings};
 use opengl_graphics::{OpenGL, GlGraphics, Filter, GlyphCache, TextureSettings};
-use piston::input::RenderEvent;
-
-pub use gameboard::Gameboard;
-pub use gameboard::{Cell, MoveDirection, SIZE, GameState};
-pub use gameboard_controller::GameboardController;
-pub use gameboard_view::{GameboardView, GameboardViewSettings};
+use piston::input::{Button, Key, PressEvent, RenderEvent};
 
-mod gameboard;
-mod gameboard_controller;
-mod gameboard_view;
+use piston_2048::{Gameboard, GameboardController, GameboardView, GameboardViewSettings};
+use piston_2048::{BestScore, BEST_SCORE_FILE, AUTOSAVE_FILE, REPLAY_FILE, load_replay, MoveLog, Theme};
+use piston_2048::args::{arg_value, parse_arg, has_flag, rules_from_args, rng_from_args, ai_from_args, animation_duration_from_args};
 
 fn main() {
 let opengl = OpenGL::V3_2;
-	let settings = WindowSettings::new("2048", [512; 2])
+	// The view lays itself out to whatever size the window is resized to.
+	let settings = WindowSettings::new("2048", [520, 640])
         .opengl(opengl)
+        .resizable(true)
   	    .exit_on_esc(true);
 	let mut window: GlutinWindow = settings.build()
 	    .expect("Could not create window");
 
-	let mut events = Events::new(EventSettings::new().lazy(true));
+	// Animations, playback and autoplay need update events to advance on their own.
+	let replay = arg_value("--replay").map(|path| load_replay(&path).expect("Could not load replay"));
+	let mut events = Events::new(EventSettings::new());
 
 	let mut gl = GlGraphics::new(opengl);
 
-	let gameboard = Gameboard::new(true);
+	let gameboard = Gameboard::with_rng(rules_from_args(), rng_from_args(), true);
+	println!("Seed: {}", gameboard.rng.seed());
 	let mut gameboard_controller = GameboardController::new(gameboard);
-	let gameboard_view_settings = GameboardViewSettings::new();
-	let gameboard_view = GameboardView::new(gameboard_view_settings);
+	gameboard_controller.best_score = BestScore::load(BEST_SCORE_FILE);
+	// `--resume` picks up the game saved when the window was last closed.
+	let load_path = arg_value("--load").or_else(|| {
+		if has_flag("--resume") { Some(AUTOSAVE_FILE.to_string()) } else { None }
+	});
+	if let Some(path) = load_path {
+		gameboard_controller.load(&path).expect("Could not load game");
+	}
+	let playback = replay.is_some();
+	if let Some(replay) = replay {
+		gameboard_controller.start_playback(replay);
+	}
+	gameboard_controller.autoplay = has_flag("--autoplay");
+	gameboard_controller.ai = ai_from_args();
+	gameboard_controller.animation_duration = animation_duration_from_args();
+
+	// `--theme` takes a built-in theme's name or a theme file; T switches
+	// between the built-in themes and the loaded one.
+	let mut themes = Theme::built_in();
+	let mut theme_index = 0;
+	if let Some(theme) = arg_value("--theme") {
+		theme_index = match themes.iter().position(|built_in| built_in.name == theme) {
+			Some(index) => index,
+			None => {
+				themes.push(Theme::load(&theme).expect("Could not load theme"));
+				themes.len() - 1
+			}
+		};
+	}
+	// `--accessible` turns on large text and tile shapes, which can also
+	// be set on their own.
+	let mut gameboard_view_settings = if has_flag("--accessible") {
+		GameboardViewSettings::accessible()
+	} else {
+		GameboardViewSettings::new()
+	};
+	gameboard_view_settings.theme = themes[theme_index].clone();
+	if let Some(scale) = arg_value("--text-scale") {
+		gameboard_view_settings.text_scale = parse_arg("--text-scale", &scale, "a number");
+	}
+	if has_flag("--markers") {
+		gameboard_view_settings.tile_markers = true;
+	}
+	let mut gameboard_view = GameboardView::new(gameboard_view_settings);
 
+    let load_font = |path: &str| {
+        let texture_settings = TextureSettings::new().filter(Filter::Nearest);
+        GlyphCache::new(path, (), texture_settings).expect("Could not load font")
+    };
+    let mut glyphs = load_font(&gameboard_view.settings.theme.font_path);
 
-    let texture_settings = TextureSettings::new().filter(Filter::Nearest);
-    let ref mut glyphs = GlyphCache::new("assets/FiraSans-Regular.ttf", (), texture_settings)
-		.expect("Could not load font");
+	// `--announce` writes every move and the board after it as text, to a
+	// file if one is given and to standard output otherwise.
+	let mut move_log: Option<MoveLog<Box<dyn Write>>> = if has_flag("--announce") {
+		let out: Box<dyn Write> = match arg_value("--announce") {
+			Some(ref path) if !path.starts_with("--") => Box::new(File::create(path).expect("Could not create announce file")),
+			_ => Box::new(io::stdout()),
+		};
+		Some(MoveLog::new(out))
+	} else {
+		None
+	};
 
 	while let Some(e) = events.next(&mut window) {
         gameboard_controller.event(&e);
+		if let Some(ref mut move_log) = move_log {
+			if let Err(err) = move_log.observe(&gameboard_controller) {
+				println!("Could not announce move: {}", err);
+			}
+		}
+		if let Some(Button::Keyboard(Key::T)) = e.press_args() {
+			theme_index = (theme_index + 1) % themes.len();
+			let theme = themes[theme_index].clone();
+			if theme.font_path != gameboard_view.settings.theme.font_path {
+				glyphs = load_font(&theme.font_path);
+			}
+			gameboard_controller.message = Some(format!("Theme: {}", theme.name));
+			gameboard_view.settings.theme = theme;
+		}
 		if let Some(args) = e.render_args() {
 			gl.draw(args.viewport(), |c, g| {
 				use::graphics::{clear};
-				clear([1.0; 4], g);
-				gameboard_view.draw(&gameboard_controller, glyphs, &c, g);
+				clear(gameboard_view.settings.theme.background_color, g);
+				gameboard_view.draw(&gameboard_controller, &mut glyphs, &c, g);
 			});
 
     	}
 	}
+
+	if playback {
+		return;
+	}
+	match gameboard_controller.save(AUTOSAVE_FILE) {
+		Ok(()) => println!("Saved game to {}", AUTOSAVE_FILE),
+		Err(err) => println!("Could not save game: {}", err),
+	}
+	if gameboard_controller.replay.is_some() {
+		match gameboard_controller.save_replay(REPLAY_FILE) {
+			Ok(()) => println!("Saved replay to {}", REPLAY_FILE),
+			Err(err) => println!("Could not save replay: {}", err),
+		}
+	}
 }
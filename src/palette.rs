@@ -0,0 +1,92 @@
+//! Tile colors shared by the window and terminal frontends.
+
+/// An RGBA color with components between 0 and 1.
+pub type Rgba = [f32; 4];
+
+/// Color of tiles without an entry of their own.
+pub const DEFAULT_TILE_COLOR: Rgba = [1.0, 0.8, 0.6, 1.0];
+
+/// Background color of a tile with value `n`.
+pub fn tile_color(n: usize) -> Rgba {
+    match n {
+        2 => [1.0, 0.8, 0.6, 1.0],
+        4 => [1.0, 0.6, 0.207, 1.0],
+        8 => [1.0, 0.5, 0.0, 1.0],
+        16 => [1.0, 0.4, 0.0, 1.0],
+        32 => [1.0, 0.3, 0.0, 1.0],
+        64 => [1.0, 0.2, 0.0, 1.0],
+        128 => [0.8, 0.2, 0.2, 1.0],
+        256 => [1.0, 1.0, 0.6, 1.0],
+        512 => [1.0, 0.7560, 0.4, 1.0],
+        1024 => [1.0, 0.6, 0.0, 1.0],
+        2048 => [0.84, 0.48, 0.149, 1.0],
+        _ => DEFAULT_TILE_COLOR,
+    }
+}
+
+/// Converts a color to 8-bit red, green and blue components.
+pub fn to_rgb8(color: Rgba) -> [u8; 3] {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [channel(color[0]), channel(color[1]), channel(color[2])]
+}
+
+/// Kinds of color blindness that `simulate` can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorBlindness {
+    /// No red cones.
+    Protanopia,
+    /// No green cones.
+    Deuteranopia,
+    /// No blue cones.
+    Tritanopia,
+}
+
+/// Approximates how `color` looks with `kind` of color blindness, using the
+/// full-severity matrices of Machado, Oliveira and Fernandes (2009).
+pub fn simulate(color: Rgba, kind: ColorBlindness) -> Rgba {
+    let matrix = match kind {
+        ColorBlindness::Protanopia => [
+            [0.152286, 1.052583, -0.204868],
+            [0.114503, 0.786281, 0.099216],
+            [-0.003882, -0.048116, 1.051998],
+        ],
+        ColorBlindness::Deuteranopia => [
+            [0.367322, 0.860646, -0.227968],
+            [0.280085, 0.672501, 0.047413],
+            [-0.011820, 0.042940, 0.968881],
+        ],
+        ColorBlindness::Tritanopia => [
+            [1.255528, -0.076749, -0.178779],
+            [-0.078411, 0.930809, 0.147602],
+            [0.004733, 0.691367, 0.303900],
+        ],
+    };
+    // The matrices work on linear light, not on sRGB values.
+    let to_linear = |c: f32| if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+    let to_srgb = |c: f32| {
+        let c = c.clamp(0.0, 1.0);
+        if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+    };
+    let linear = [to_linear(color[0]), to_linear(color[1]), to_linear(color[2])];
+    let mut simulated = color;
+    for (channel, row) in simulated.iter_mut().zip(matrix.iter()) {
+        *channel = to_srgb(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]);
+    }
+    simulated
+}
+
+/// Straight-line distance between two colors, ignoring alpha.
+pub fn distance(a: Rgba, b: Rgba) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use palette::{tile_color, to_rgb8, DEFAULT_TILE_COLOR};
+
+    #[test]
+    fn unknown_tiles_use_the_default_color() {
+        assert_eq!(tile_color(4096), DEFAULT_TILE_COLOR);
+        assert_eq!(to_rgb8(tile_color(8)), [255, 128, 0]);
+    }
+}
@@ -0,0 +1,231 @@
+//! Replay recording and playback.
+
+use {Gameboard, GameRng, GameState, MoveDirection, Rules};
+
+/// Longest pause between two moves during playback, in seconds.
+const MAX_PAUSE: f64 = 2.0;
+
+/// A move in a replay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayMove {
+    pub direction: MoveDirection,
+    /// Milliseconds since the game started.
+    pub time_ms: u64,
+}
+
+/// Everything needed to play a game again.
+///
+/// Only moves that changed the board are recorded, so playing them from
+/// a board with the same seed and rules reproduces every spawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub rules: Rules,
+    pub moves: Vec<ReplayMove>,
+}
+
+impl Replay {
+    /// Creates an empty replay of the game started by `gameboard`.
+    pub fn new(gameboard: &Gameboard) -> Replay {
+        Replay {
+            seed: gameboard.rng.seed(),
+            rules: gameboard.rules.clone(),
+            moves: Vec::new(),
+        }
+    }
+
+    /// Records a move made after `moves_before` earlier moves, dropping any
+    /// moves that were undone since.
+    pub fn record(&mut self, moves_before: usize, direction: MoveDirection, time_ms: u64) {
+        self.moves.truncate(moves_before);
+        self.moves.push(ReplayMove {
+            direction: direction,
+            time_ms: time_ms,
+        });
+    }
+
+    /// Board the game started with.
+    pub fn start(&self) -> Gameboard {
+        Gameboard::with_rng(self.rules.clone(), GameRng::new(self.seed), true)
+    }
+
+    /// Board and game state after the first `n` moves.
+    pub fn board_after(&self, n: usize) -> (Gameboard, GameState) {
+        let mut gameboard = self.start();
+        let mut game_state = GameState::Playing;
+        for replay_move in self.moves.iter().take(n) {
+            game_state = gameboard.handle_move(replay_move.direction);
+        }
+        (gameboard, game_state)
+    }
+}
+
+/// Steps through a replay.
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    /// Number of moves applied to `gameboard`.
+    pub position: usize,
+    pub gameboard: Gameboard,
+    pub game_state: GameState,
+    /// Whether moves are applied automatically on update.
+    pub playing: bool,
+    /// Playback speed relative to the recording.
+    pub speed: f64,
+    /// Seconds since the last move was applied while playing.
+    waited: f64,
+}
+
+impl ReplayPlayer {
+    /// Creates a paused player at the start of `replay`.
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        let gameboard = replay.start();
+        ReplayPlayer {
+            replay: replay,
+            position: 0,
+            gameboard: gameboard,
+            game_state: GameState::Playing,
+            playing: false,
+            speed: 1.0,
+            waited: 0.0,
+        }
+    }
+
+    /// Total number of moves.
+    pub fn len(&self) -> usize {
+        self.replay.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replay.moves.is_empty()
+    }
+
+    /// Applies the next move. Returns false at the end of the replay.
+    pub fn step_forward(&mut self) -> bool {
+        match self.replay.moves.get(self.position) {
+            Some(replay_move) => {
+                self.game_state = self.gameboard.handle_move(replay_move.direction);
+                self.position += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes back the last move. Returns false at the start of the replay.
+    pub fn step_back(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        let position = self.position - 1;
+        self.jump_to(position);
+        true
+    }
+
+    /// Shows the board after `position` moves, or after the last one if beyond the end.
+    pub fn jump_to(&mut self, position: usize) {
+        let position = ::std::cmp::min(position, self.len());
+        if position >= self.position {
+            while self.position < position {
+                self.step_forward();
+            }
+        } else {
+            let (gameboard, game_state) = self.replay.board_after(position);
+            self.gameboard = gameboard;
+            self.game_state = game_state;
+            self.position = position;
+        }
+        self.waited = 0.0;
+    }
+
+    /// Starts or pauses automatic playback.
+    pub fn toggle_playing(&mut self) {
+        self.playing = !self.playing;
+        self.waited = 0.0;
+    }
+
+    /// Seconds to wait before the next move when playing.
+    fn next_pause(&self) -> f64 {
+        let moves = &self.replay.moves;
+        let previous_ms = if self.position == 0 { 0 } else { moves[self.position - 1].time_ms };
+        let pause = match moves.get(self.position) {
+            Some(next) => next.time_ms.saturating_sub(previous_ms) as f64 / 1000.0,
+            None => 0.0,
+        };
+        pause.min(MAX_PAUSE) / self.speed
+    }
+
+    /// Advances playback by `dt` seconds. Returns whether the board changed.
+    pub fn update(&mut self, dt: f64) -> bool {
+        if !self.playing {
+            return false;
+        }
+        self.waited += dt;
+        let mut changed = false;
+        while self.playing && self.waited >= self.next_pause() {
+            self.waited -= self.next_pause();
+            if self.step_forward() {
+                changed = true;
+            } else {
+                self.playing = false;
+                self.waited = 0.0;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Gameboard, MoveDirection, Replay, ReplayPlayer};
+
+    fn recorded_game() -> (Gameboard, Replay) {
+        let mut gameboard = Gameboard::with_seed(11, true);
+        let mut replay = Replay::new(&gameboard);
+        let moves = [MoveDirection::Left, MoveDirection::Up, MoveDirection::Right, MoveDirection::Down];
+        for (i, &movement) in moves.iter().cycle().take(30).enumerate() {
+            let moves_before = gameboard.moves;
+            gameboard.handle_move(movement);
+            if gameboard.moves > moves_before {
+                replay.record(moves_before, movement, i as u64 * 100);
+            }
+        }
+        (gameboard, replay)
+    }
+
+    #[test]
+    fn replay_reproduces_the_game() {
+        let (gameboard, replay) = recorded_game();
+        let (replayed, _) = replay.board_after(replay.moves.len());
+        assert_eq!(replayed.cells, gameboard.cells);
+        assert_eq!(replayed.score, gameboard.score);
+    }
+
+    #[test]
+    fn player_steps_both_ways_and_jumps() {
+        let (gameboard, replay) = recorded_game();
+        let mut player = ReplayPlayer::new(replay.clone());
+        player.jump_to(player.len());
+        assert_eq!(player.gameboard.cells, gameboard.cells);
+        assert!(player.step_back());
+        assert_eq!(player.gameboard.cells, replay.board_after(player.len() - 1).0.cells);
+        player.jump_to(3);
+        assert_eq!(player.gameboard.cells, replay.board_after(3).0.cells);
+        assert!(player.step_forward());
+        assert_eq!(player.position, 4);
+    }
+
+    #[test]
+    fn playing_follows_timestamps_and_speed() {
+        let (_, replay) = recorded_game();
+        let mut player = ReplayPlayer::new(replay);
+        player.speed = 2.0;
+        player.toggle_playing();
+        player.update(0.01);
+        let first = player.position;
+        player.update(1.0);
+        assert!(player.position > first);
+        player.update(1000.0);
+        assert_eq!(player.position, player.len());
+        assert!(!player.playing);
+    }
+}
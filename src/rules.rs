@@ -0,0 +1,179 @@
+//! Game rules.
+
+use {GameRng, SIZE};
+
+/// How merged tiles behave during the rest of a move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeRule {
+    /// A tile takes part in at most one merge per move, as in the original game.
+    OncePerMove,
+    /// A freshly merged tile can merge again in the same move.
+    Cascading,
+}
+
+/// Number of cells spawned at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpawnCount {
+    Exact(usize),
+    /// Any count between the two bounds, inclusive, given in either order.
+    Range(usize, usize),
+}
+
+impl SpawnCount {
+    /// Smallest and largest count, in that order.
+    pub fn bounds(&self) -> (usize, usize) {
+        match *self {
+            SpawnCount::Exact(n) => (n, n),
+            SpawnCount::Range(a, b) => (a.min(b), a.max(b)),
+        }
+    }
+
+    /// Picks a count.
+    pub fn sample(&self, rng: &mut GameRng) -> usize {
+        let (low, high) = self.bounds();
+        match (high - low).checked_add(1) {
+            Some(span) => low + rng.gen_range(0, span),
+            // The range covers every count.
+            None => rng.next_u64() as usize,
+        }
+    }
+}
+
+/// Controls which cells appear at the start of a game and after every move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpawnRules {
+    /// Spawned values with their relative weights.
+    pub values: Vec<(usize, u32)>,
+    /// Cells placed when the board is set up.
+    pub initial_count: SpawnCount,
+    /// Cells placed after every move.
+    pub per_move_count: SpawnCount,
+}
+
+impl SpawnRules {
+    /// Creates the spawn rules of the original game: one cell per move,
+    /// a 2 nine times out of ten and a 4 otherwise.
+    pub fn new() -> SpawnRules {
+        SpawnRules {
+            values: vec![(2, 9), (4, 1)],
+            initial_count: SpawnCount::Exact(2),
+            per_move_count: SpawnCount::Exact(1),
+        }
+    }
+
+    /// Picks a value to spawn according to the weights.
+    pub fn sample_value(&self, rng: &mut GameRng) -> usize {
+        let total: u32 = self.values.iter().map(|&(_, weight)| weight).sum();
+        assert!(total > 0, "spawn rules need at least one weighted value");
+        let mut roll = rng.gen_range(0, total as usize) as u32;
+        for &(value, weight) in self.values.iter() {
+            if roll < weight {
+                return value;
+            }
+            roll -= weight;
+        }
+        unreachable!()
+    }
+}
+
+impl Default for SpawnRules {
+    fn default() -> SpawnRules {
+        SpawnRules::new()
+    }
+}
+
+/// What happens once the target tile is reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinPolicy {
+    /// The game ends with the first win.
+    Stop,
+    /// The game goes on without telling the player.
+    ContinueSilently,
+    /// The player is told about the win and continues with the next move.
+    Prompt,
+}
+
+/// Controls when the game is won.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WinRules {
+    /// Tile value that wins the game.
+    pub target: usize,
+    /// Tile values that fire an event the first time they appear.
+    pub milestones: Vec<usize>,
+    pub policy: WinPolicy,
+}
+
+impl WinRules {
+    /// Creates the win rules of the original game.
+    pub fn new() -> WinRules {
+        WinRules {
+            target: 2048,
+            milestones: vec![2048, 4096, 8192],
+            policy: WinPolicy::Prompt,
+        }
+    }
+}
+
+impl Default for WinRules {
+    fn default() -> WinRules {
+        WinRules::new()
+    }
+}
+
+/// How many moves a game allows to undo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UndoBudget {
+    Unlimited,
+    PerGame(usize),
+    Disabled,
+}
+
+/// Rules the game board is played by.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rules {
+    /// Number of columns.
+    pub width: usize,
+    /// Number of rows.
+    pub height: usize,
+    pub merge_rule: MergeRule,
+    pub spawn: SpawnRules,
+    pub win: WinRules,
+    pub undo_budget: UndoBudget,
+}
+
+impl Rules {
+    /// Creates the rules of the original game.
+    pub fn new() -> Rules {
+        Rules {
+            width: SIZE,
+            height: SIZE,
+            merge_rule: MergeRule::OncePerMove,
+            spawn: SpawnRules::new(),
+            win: WinRules::new(),
+            undo_budget: UndoBudget::Unlimited,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {GameRng, SpawnCount};
+
+    #[test]
+    fn spawn_count_accepts_any_bounds() {
+        let mut rng = GameRng::new(3);
+        for _ in 0..100 {
+            let n = SpawnCount::Range(3, 1).sample(&mut rng);
+            assert!((1..=3).contains(&n));
+        }
+        assert_eq!(SpawnCount::Range(2, 2).sample(&mut rng), 2);
+        SpawnCount::Range(0, usize::MAX).sample(&mut rng);
+        assert!(SpawnCount::Range(5, usize::MAX).sample(&mut rng) >= 5);
+    }
+}
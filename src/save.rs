@@ -0,0 +1,612 @@
+//! Saving and loading games.
+//!
+//! Saves are plain text, one `key value` pair per line. The first line is
+//! `2048-save <version>`; the current version is 2. The rules follow:
+//!
+//! ```text
+//! 2048-save 2
+//! width 4
+//! height 4
+//! merge-rule once-per-move        (or cascading)
+//! spawn-values 2:9 4:1            (value:weight pairs)
+//! spawn-initial 2                 (a count, or low-high for a range)
+//! spawn-per-move 1
+//! win-target 2048
+//! win-milestones 2048 4096 8192
+//! win-policy prompt               (or stop, continue)
+//! undo-budget unlimited           (or none, or a count)
+//! history-capacity 256
+//! undos-used 0
+//! play-time 61.5                  (seconds, missing from version 1)
+//! ```
+//!
+//! Then the current game, followed by every position that can be undone
+//! (oldest first) and every position that can be redone. Each is a block
+//! opened by `game`, `undo` or `redo` and closed by `end`:
+//!
+//! ```text
+//! game
+//! state playing                   (or won, lost)
+//! seed 1234
+//! rng-state 5678
+//! score 16
+//! moves 7
+//! won false
+//! milestones-reached
+//! row 2 0 0 0                     (one line per row, 0 is empty)
+//! row 0 8 0 0
+//! row 0 0 0 0
+//! row 4 0 0 2
+//! end
+//! ```
+//!
+//! Replays use the same layout. The first line is `2048-replay <version>`,
+//! again currently 1, followed by the rules up to `undo-budget`, the seed
+//! and one line per move with the milliseconds since the game started:
+//!
+//! ```text
+//! seed 1234
+//! move left 0
+//! move up 850
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use {Gameboard, GameState, GameRng, Cell, History, Snapshot};
+use {MoveDirection, Replay, ReplayMove};
+use {Rules, MergeRule, SpawnCount, WinPolicy, UndoBudget};
+
+/// Version written by this build.
+pub const SAVE_VERSION: u32 = 2;
+/// File used by quicksave and quickload.
+pub const QUICKSAVE_FILE: &'static str = "quicksave.2048";
+/// File the game is saved to when the window closes.
+pub const AUTOSAVE_FILE: &'static str = "autosave.2048";
+/// Replay version written by this build.
+pub const REPLAY_VERSION: u32 = 1;
+/// File the replay of the current game is saved to.
+pub const REPLAY_FILE: &'static str = "last_game.2048replay";
+
+/// Error from saving or loading a game.
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    /// The save was written by an unknown version.
+    UnsupportedVersion(u32),
+    /// The save is malformed at the given line, counted from 1.
+    Format { line: usize, message: String },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref err) => write!(f, "{}", err),
+            SaveError::UnsupportedVersion(version) => write!(f, "unsupported save version {}", version),
+            SaveError::Format { line, ref message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> SaveError {
+        SaveError::Io(err)
+    }
+}
+
+/// Saves the current game, its history and the seconds spent playing it
+/// to `path`.
+pub fn save_game<P: AsRef<Path>>(path: P, current: &Snapshot, history: &History<Snapshot>, play_time: f64)
+        -> Result<(), SaveError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_game(&mut writer, current, history, play_time)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Loads a game, its history and the seconds spent playing it from `path`.
+pub fn load_game<P: AsRef<Path>>(path: P) -> Result<(Snapshot, History<Snapshot>, f64), SaveError> {
+    read_game(BufReader::new(File::open(path)?))
+}
+
+/// Writes the current game, its history and the seconds spent playing it.
+pub fn write_game<W: Write>(w: &mut W, current: &Snapshot, history: &History<Snapshot>, play_time: f64)
+        -> Result<(), SaveError> {
+    writeln!(w, "2048-save {}", SAVE_VERSION)?;
+    write_rules(w, &current.gameboard.rules)?;
+    writeln!(w, "history-capacity {}", history.capacity())?;
+    writeln!(w, "undos-used {}", history.undos_used)?;
+    writeln!(w, "play-time {}", play_time)?;
+    write_snapshot(w, "game", current)?;
+    for snapshot in history.undo_states() {
+        write_snapshot(w, "undo", snapshot)?;
+    }
+    for snapshot in history.redo_states() {
+        write_snapshot(w, "redo", snapshot)?;
+    }
+    Ok(())
+}
+
+/// Saves a replay to `path`.
+pub fn save_replay<P: AsRef<Path>>(path: P, replay: &Replay) -> Result<(), SaveError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_replay(&mut writer, replay)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Loads a replay from `path`.
+pub fn load_replay<P: AsRef<Path>>(path: P) -> Result<Replay, SaveError> {
+    read_replay(BufReader::new(File::open(path)?))
+}
+
+/// Writes a replay.
+pub fn write_replay<W: Write>(w: &mut W, replay: &Replay) -> Result<(), SaveError> {
+    writeln!(w, "2048-replay {}", REPLAY_VERSION)?;
+    write_rules(w, &replay.rules)?;
+    writeln!(w, "seed {}", replay.seed)?;
+    for replay_move in replay.moves.iter() {
+        let direction = match replay_move.direction {
+            MoveDirection::Up => "up",
+            MoveDirection::Right => "right",
+            MoveDirection::Down => "down",
+            MoveDirection::Left => "left",
+        };
+        writeln!(w, "move {} {}", direction, replay_move.time_ms)?;
+    }
+    Ok(())
+}
+
+/// Reads a replay.
+pub fn read_replay<R: BufRead>(r: R) -> Result<Replay, SaveError> {
+    let mut lines = Lines::new(r);
+    let version: u32 = lines.value("2048-replay")?;
+    if version != REPLAY_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+    let rules = read_rules(&mut lines)?;
+    let seed = lines.value("seed")?;
+    let mut moves = Vec::new();
+    while lines.has_more()? {
+        let text = lines.text("move")?;
+        let mut parts = text.split_whitespace();
+        let direction = match parts.next() {
+            Some("up") => MoveDirection::Up,
+            Some("right") => MoveDirection::Right,
+            Some("down") => MoveDirection::Down,
+            Some("left") => MoveDirection::Left,
+            _ => return Err(lines.error(format!("invalid move `{}`", text))),
+        };
+        let time_ms = match parts.next().map(|time| time.parse()) {
+            Some(Ok(time_ms)) => time_ms,
+            _ => return Err(lines.error(format!("invalid move time in `{}`", text))),
+        };
+        moves.push(ReplayMove {
+            direction: direction,
+            time_ms: time_ms,
+        });
+    }
+    Ok(Replay {
+        seed: seed,
+        rules: rules,
+        moves: moves,
+    })
+}
+
+fn write_rules<W: Write>(w: &mut W, rules: &Rules) -> Result<(), SaveError> {
+    writeln!(w, "width {}", rules.width)?;
+    writeln!(w, "height {}", rules.height)?;
+    writeln!(w, "merge-rule {}", match rules.merge_rule {
+        MergeRule::OncePerMove => "once-per-move",
+        MergeRule::Cascading => "cascading",
+    })?;
+    let values: Vec<String> = rules.spawn.values.iter()
+        .map(|&(value, weight)| format!("{}:{}", value, weight))
+        .collect();
+    writeln!(w, "spawn-values {}", values.join(" "))?;
+    writeln!(w, "spawn-initial {}", format_spawn_count(rules.spawn.initial_count))?;
+    writeln!(w, "spawn-per-move {}", format_spawn_count(rules.spawn.per_move_count))?;
+    writeln!(w, "win-target {}", rules.win.target)?;
+    writeln!(w, "win-milestones {}", join_numbers(&rules.win.milestones))?;
+    writeln!(w, "win-policy {}", match rules.win.policy {
+        WinPolicy::Stop => "stop",
+        WinPolicy::ContinueSilently => "continue",
+        WinPolicy::Prompt => "prompt",
+    })?;
+    match rules.undo_budget {
+        UndoBudget::Unlimited => writeln!(w, "undo-budget unlimited")?,
+        UndoBudget::Disabled => writeln!(w, "undo-budget none")?,
+        UndoBudget::PerGame(n) => writeln!(w, "undo-budget {}", n)?,
+    }
+    Ok(())
+}
+
+fn format_spawn_count(count: SpawnCount) -> String {
+    match count {
+        SpawnCount::Exact(n) => n.to_string(),
+        SpawnCount::Range(..) => {
+            let (low, high) = count.bounds();
+            format!("{}-{}", low, high)
+        }
+    }
+}
+
+fn join_numbers(numbers: &[usize]) -> String {
+    let numbers: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+    numbers.join(" ")
+}
+
+fn write_snapshot<W: Write>(w: &mut W, block: &str, snapshot: &Snapshot) -> Result<(), SaveError> {
+    let ref gameboard = snapshot.gameboard;
+    writeln!(w, "{}", block)?;
+    writeln!(w, "state {}", match snapshot.game_state {
+        GameState::Playing => "playing",
+        GameState::Won => "won",
+        GameState::Lost => "lost",
+    })?;
+    writeln!(w, "seed {}", gameboard.rng.seed())?;
+    writeln!(w, "rng-state {}", gameboard.rng.state())?;
+    writeln!(w, "score {}", gameboard.score)?;
+    writeln!(w, "moves {}", gameboard.moves)?;
+    writeln!(w, "won {}", gameboard.has_already_won)?;
+    writeln!(w, "milestones-reached {}", join_numbers(&gameboard.milestones_reached))?;
+    for y in 0..gameboard.height() {
+        let row: Vec<String> = (0..gameboard.width()).map(|x| gameboard.cells[x][y].to_string()).collect();
+        writeln!(w, "row {}", row.join(" "))?;
+    }
+    writeln!(w, "end")?;
+    Ok(())
+}
+
+/// Reads a game, its history and the seconds spent playing it.
+pub fn read_game<R: BufRead>(r: R) -> Result<(Snapshot, History<Snapshot>, f64), SaveError> {
+    let mut lines = Lines::new(r);
+
+    let version: u32 = lines.value("2048-save")?;
+    if version == 0 || version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+    let rules = read_rules(&mut lines)?;
+    let capacity = lines.value("history-capacity")?;
+    let undos_used = lines.value("undos-used")?;
+    let play_time: f64 = if version >= 2 { lines.value("play-time")? } else { 0.0 };
+    if !(play_time >= 0.0 && play_time.is_finite()) {
+        return Err(lines.error(format!("invalid play-time `{}`", play_time)));
+    }
+
+    lines.expect("game")?;
+    let current = read_snapshot(&mut lines, &rules)?;
+    let mut undo = Vec::new();
+    let mut redo = Vec::new();
+    while let Some(block) = lines.next_line()? {
+        match block.as_str() {
+            "undo" => undo.push(read_snapshot(&mut lines, &rules)?),
+            "redo" => redo.push(read_snapshot(&mut lines, &rules)?),
+            other => return Err(lines.error(format!("expected undo or redo, found `{}`", other))),
+        }
+    }
+    let history = History::from_parts(capacity, rules.undo_budget, undo, redo, undos_used);
+    Ok((current, history, play_time))
+}
+
+/// Whether `n` is a value a tile can have.
+fn is_tile(n: usize) -> bool {
+    n >= 2 && n.is_power_of_two()
+}
+
+fn read_rules<R: BufRead>(lines: &mut Lines<R>) -> Result<Rules, SaveError> {
+    let mut rules = Rules::new();
+    rules.width = lines.value("width")?;
+    rules.height = lines.value("height")?;
+    if !::is_supported_size(rules.width, rules.height) {
+        return Err(lines.error(format!("unsupported board size {}x{}", rules.width, rules.height)));
+    }
+    rules.merge_rule = match lines.text("merge-rule")?.as_str() {
+        "once-per-move" => MergeRule::OncePerMove,
+        "cascading" => MergeRule::Cascading,
+        other => return Err(lines.error(format!("unknown merge rule `{}`", other))),
+    };
+    let spawn_values = lines.text("spawn-values")?;
+    rules.spawn.values = Vec::new();
+    for pair in spawn_values.split_whitespace() {
+        let mut parts = pair.splitn(2, ':');
+        let value = parts.next().and_then(|value| value.parse().ok());
+        let weight = parts.next().and_then(|weight| weight.parse().ok());
+        match (value, weight) {
+            (Some(value), Some(weight)) if is_tile(value) => rules.spawn.values.push((value, weight)),
+            (Some(value), Some(_)) => return Err(lines.error(format!("{} is not a power of two", value))),
+            _ => return Err(lines.error(format!("expected value:weight, found `{}`", pair))),
+        }
+    }
+    if rules.spawn.values.iter().all(|&(_, weight)| weight == 0) {
+        return Err(lines.error("spawn values need a positive weight".to_string()));
+    }
+    rules.spawn.initial_count = lines.spawn_count("spawn-initial")?;
+    rules.spawn.per_move_count = lines.spawn_count("spawn-per-move")?;
+    rules.win.target = lines.value("win-target")?;
+    if rules.win.target == 0 {
+        return Err(lines.error("win target can't be zero".to_string()));
+    }
+    rules.win.milestones = lines.numbers("win-milestones")?;
+    rules.win.policy = match lines.text("win-policy")?.as_str() {
+        "stop" => WinPolicy::Stop,
+        "continue" => WinPolicy::ContinueSilently,
+        "prompt" => WinPolicy::Prompt,
+        other => return Err(lines.error(format!("unknown win policy `{}`", other))),
+    };
+    rules.undo_budget = match lines.text("undo-budget")?.as_str() {
+        "unlimited" => UndoBudget::Unlimited,
+        "none" => UndoBudget::Disabled,
+        n => match n.parse() {
+            Ok(n) => UndoBudget::PerGame(n),
+            Err(_) => return Err(lines.error(format!("unknown undo budget `{}`", n))),
+        },
+    };
+    Ok(rules)
+}
+
+fn read_snapshot<R: BufRead>(lines: &mut Lines<R>, rules: &Rules) -> Result<Snapshot, SaveError> {
+    let game_state = match lines.text("state")?.as_str() {
+        "playing" => GameState::Playing,
+        "won" => GameState::Won,
+        "lost" => GameState::Lost,
+        other => return Err(lines.error(format!("unknown game state `{}`", other))),
+    };
+    let seed = lines.value("seed")?;
+    let state = lines.value("rng-state")?;
+    if state == 0 {
+        return Err(lines.error("rng state can't be zero".to_string()));
+    }
+    let mut gameboard = Gameboard::with_rng(rules.clone(), GameRng::from_state(seed, state), false);
+    gameboard.score = lines.value("score")?;
+    gameboard.moves = lines.value("moves")?;
+    gameboard.has_already_won = lines.value("won")?;
+    gameboard.milestones_reached = lines.numbers("milestones-reached")?;
+    for y in 0..rules.height {
+        let row = lines.numbers("row")?;
+        if row.len() != rules.width {
+            return Err(lines.error(format!("expected {} cells, found {}", rules.width, row.len())));
+        }
+        for (x, &n) in row.iter().enumerate() {
+            gameboard.cells[x][y] = match n {
+                0 => Cell::Empty,
+                n if is_tile(n) => Cell::Occupied(n),
+                n => return Err(lines.error(format!("{} is not a power of two", n))),
+            };
+        }
+    }
+    lines.expect("end")?;
+    Ok(Snapshot {
+        gameboard: gameboard,
+        game_state: game_state,
+    })
+}
+
+/// Reads `key value` lines while keeping track of the line number.
+struct Lines<R> {
+    lines: io::Lines<R>,
+    line: usize,
+    peeked: Option<String>,
+}
+
+impl<R: BufRead> Lines<R> {
+    fn new(r: R) -> Lines<R> {
+        Lines {
+            lines: r.lines(),
+            line: 0,
+            peeked: None,
+        }
+    }
+
+    fn error(&self, message: String) -> SaveError {
+        SaveError::Format {
+            line: self.line,
+            message: message,
+        }
+    }
+
+    /// Whether any non-blank lines are left.
+    fn has_more(&mut self) -> Result<bool, SaveError> {
+        if self.peeked.is_none() {
+            self.peeked = self.next_line()?;
+        }
+        Ok(self.peeked.is_some())
+    }
+
+    /// Returns the next non-blank line, or `None` at the end.
+    fn next_line(&mut self) -> Result<Option<String>, SaveError> {
+        if let Some(line) = self.peeked.take() {
+            return Ok(Some(line));
+        }
+        for line in &mut self.lines {
+            let line = line?;
+            self.line += 1;
+            if !line.trim().is_empty() {
+                return Ok(Some(line.trim().to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), SaveError> {
+        match self.next_line()? {
+            Some(ref line) if line == expected => Ok(()),
+            Some(line) => Err(self.error(format!("expected `{}`, found `{}`", expected, line))),
+            None => Err(self.error(format!("expected `{}`, found end of file", expected))),
+        }
+    }
+
+    /// Returns whatever follows `key` on the next line.
+    fn text(&mut self, key: &str) -> Result<String, SaveError> {
+        let line = match self.next_line()? {
+            Some(line) => line,
+            None => return Err(self.error(format!("expected `{}`, found end of file", key))),
+        };
+        let mut parts = line.splitn(2, ' ');
+        if parts.next() != Some(key) {
+            return Err(self.error(format!("expected `{}`, found `{}`", key, line)));
+        }
+        Ok(parts.next().unwrap_or("").trim().to_string())
+    }
+
+    fn value<T: ::std::str::FromStr>(&mut self, key: &str) -> Result<T, SaveError> {
+        let text = self.text(key)?;
+        text.parse().map_err(|_| self.error(format!("invalid {} `{}`", key, text)))
+    }
+
+    fn numbers(&mut self, key: &str) -> Result<Vec<usize>, SaveError> {
+        let text = self.text(key)?;
+        let mut numbers = Vec::new();
+        for n in text.split_whitespace() {
+            match n.parse() {
+                Ok(n) => numbers.push(n),
+                Err(_) => return Err(self.error(format!("invalid number `{}` in {}", n, key))),
+            }
+        }
+        Ok(numbers)
+    }
+
+    fn spawn_count(&mut self, key: &str) -> Result<SpawnCount, SaveError> {
+        let text = self.text(key)?;
+        let mut bounds = text.splitn(2, '-').map(|n| n.parse::<usize>());
+        match (bounds.next(), bounds.next()) {
+            (Some(Ok(n)), None) => Ok(SpawnCount::Exact(n)),
+            (Some(Ok(a)), Some(Ok(b))) => Ok(SpawnCount::Range(a.min(b), a.max(b))),
+            _ => Err(self.error(format!("invalid {} `{}`", key, text))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Gameboard, GameState, History, Snapshot, MoveDirection, UndoBudget, SpawnCount};
+    use {Replay, ReplayMove};
+    use save::{write_game, read_game, write_replay, read_replay, SaveError};
+
+    #[test]
+    fn saved_game_loads_back() {
+        let mut gameboard = Gameboard::with_seed(5, true);
+        gameboard.rules.spawn.per_move_count = SpawnCount::Range(1, 2);
+        gameboard.rules.undo_budget = UndoBudget::PerGame(3);
+        let mut history = History::new(16, gameboard.rules.undo_budget);
+        for &movement in [MoveDirection::Left, MoveDirection::Up, MoveDirection::Right].iter() {
+            history.record(Snapshot { gameboard: gameboard.clone(), game_state: GameState::Playing });
+            gameboard.handle_move(movement);
+        }
+        let current = Snapshot { gameboard: gameboard, game_state: GameState::Playing };
+        let current = history.undo(&current).unwrap();
+
+        let mut saved = Vec::new();
+        write_game(&mut saved, &current, &history, 61.5).unwrap();
+        let (loaded, loaded_history, play_time) = read_game(&saved[..]).unwrap();
+        assert_eq!(play_time, 61.5);
+        assert_eq!(loaded.gameboard.cells, current.gameboard.cells);
+        assert_eq!(loaded.gameboard.rng, current.gameboard.rng);
+        assert_eq!(loaded.gameboard.rules, current.gameboard.rules);
+        assert_eq!(loaded.gameboard.score, current.gameboard.score);
+        assert_eq!(loaded_history.undo_states().len(), 2);
+        assert_eq!(loaded_history.redo_states().len(), 1);
+        assert_eq!(loaded_history.undos_remaining(), Some(2));
+
+        let mut saved_again = Vec::new();
+        write_game(&mut saved_again, &loaded, &loaded_history, play_time).unwrap();
+        assert_eq!(saved, saved_again);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        match read_game("2048-save 99\n".as_bytes()) {
+            Err(SaveError::UnsupportedVersion(99)) => (),
+            other => panic!("Unexpected result: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn malformed_row_reports_line() {
+        let gameboard = Gameboard::with_seed(5, true);
+        let current = Snapshot { gameboard: gameboard, game_state: GameState::Playing };
+        let mut saved = Vec::new();
+        write_game(&mut saved, &current, &History::new(16, UndoBudget::Unlimited), 0.0).unwrap();
+        let saved = String::from_utf8(saved).unwrap().replacen("row ", "row x ", 1);
+        match read_game(saved.as_bytes()) {
+            Err(SaveError::Format { line: 23, .. }) => (),
+            other => panic!("Unexpected result: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn impossible_tile_reports_line() {
+        let gameboard = Gameboard::with_seed(5, false);
+        let current = Snapshot { gameboard: gameboard, game_state: GameState::Playing };
+        let mut saved = Vec::new();
+        write_game(&mut saved, &current, &History::new(16, UndoBudget::Unlimited), 0.0).unwrap();
+        let saved = String::from_utf8(saved).unwrap().replacen("row 0", "row 6", 1);
+        match read_game(saved.as_bytes()) {
+            Err(SaveError::Format { line: 23, ref message }) => assert_eq!(message, "6 is not a power of two"),
+            other => panic!("Unexpected result: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn version_1_saves_load_without_play_time() {
+        let current = Snapshot { gameboard: Gameboard::with_seed(5, true), game_state: GameState::Playing };
+        let mut saved = Vec::new();
+        write_game(&mut saved, &current, &History::new(16, UndoBudget::Unlimited), 12.0).unwrap();
+        let saved = String::from_utf8(saved).unwrap().replace("2048-save 2", "2048-save 1").replace("play-time 12\n", "");
+        let (loaded, _, play_time) = read_game(saved.as_bytes()).unwrap();
+        assert_eq!(loaded.gameboard.cells, current.gameboard.cells);
+        assert_eq!(play_time, 0.0);
+    }
+
+    #[test]
+    fn reversed_spawn_range_loads_back() {
+        let mut gameboard = Gameboard::with_seed(5, true);
+        gameboard.rules.spawn.initial_count = SpawnCount::Range(3, 1);
+        let current = Snapshot { gameboard: gameboard, game_state: GameState::Playing };
+        let mut saved = Vec::new();
+        write_game(&mut saved, &current, &History::new(16, UndoBudget::Unlimited), 0.0).unwrap();
+        assert!(String::from_utf8(saved.clone()).unwrap().contains("spawn-initial 1-3\n"));
+        let (loaded, _, _) = read_game(&saved[..]).unwrap();
+        assert_eq!(loaded.gameboard.rules.spawn.initial_count, SpawnCount::Range(1, 3));
+
+        let reversed = String::from_utf8(saved).unwrap().replace("spawn-initial 1-3", "spawn-initial 3-1");
+        let (loaded, _, _) = read_game(reversed.as_bytes()).unwrap();
+        assert_eq!(loaded.gameboard.rules.spawn.initial_count.bounds(), (1, 3));
+    }
+
+    #[test]
+    fn impossible_rules_are_rejected() {
+        let gameboard = Gameboard::with_seed(5, false);
+        let current = Snapshot { gameboard: gameboard, game_state: GameState::Playing };
+        let mut saved = Vec::new();
+        write_game(&mut saved, &current, &History::new(16, UndoBudget::Unlimited), 0.0).unwrap();
+        let saved = String::from_utf8(saved).unwrap();
+        for &(from, to, expected) in [
+            ("spawn-values 2:9", "spawn-values 3:9", "3 is not a power of two"),
+            ("win-target 2048", "win-target 0", "win target can't be zero"),
+        ].iter() {
+            match read_game(saved.replace(from, to).as_bytes()) {
+                Err(SaveError::Format { ref message, .. }) => assert_eq!(message, expected),
+                other => panic!("Unexpected result: {:?}", other.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn replay_file_loads_back() {
+        let gameboard = Gameboard::with_seed(77, true);
+        let mut replay = Replay::new(&gameboard);
+        replay.record(0, MoveDirection::Left, 0);
+        replay.record(1, MoveDirection::Down, 1250);
+        let mut saved = Vec::new();
+        write_replay(&mut saved, &replay).unwrap();
+        let loaded = read_replay(&saved[..]).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.moves[1], ReplayMove { direction: MoveDirection::Down, time_ms: 1250 });
+    }
+}
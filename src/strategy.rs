@@ -0,0 +1,244 @@
+//! Players that pick moves on their own, and a harness to compare them.
+
+use {Cell, Cells, Expectimax, GameRng, GameState, Gameboard, Heuristic, MoveDirection};
+
+/// Something that picks moves.
+pub trait Strategy {
+    /// Short name for reports.
+    fn name(&self) -> String;
+
+    /// Move to make on `gameboard`, or `None` if no move changes it.
+    fn choose(&mut self, gameboard: &Gameboard) -> Option<MoveDirection>;
+}
+
+fn empty_count(cells: &Cells) -> usize {
+    cells.iter()
+        .flat_map(|column| column.iter())
+        .filter(|cell| **cell == Cell::Empty)
+        .count()
+}
+
+/// Picks any move that changes the board.
+pub struct RandomStrategy {
+    pub rng: GameRng,
+}
+
+impl RandomStrategy {
+    pub fn new(seed: u64) -> RandomStrategy {
+        RandomStrategy {
+            rng: GameRng::new(seed),
+        }
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn name(&self) -> String {
+        "random".to_string()
+    }
+
+    fn choose(&mut self, gameboard: &Gameboard) -> Option<MoveDirection> {
+        let moves = gameboard.legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        Some(moves[self.rng.gen_range(0, moves.len())])
+    }
+}
+
+/// Takes the move that scores the most right now, then the one leaving
+/// the most empty cells.
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn name(&self) -> String {
+        "greedy".to_string()
+    }
+
+    fn choose(&mut self, gameboard: &Gameboard) -> Option<MoveDirection> {
+        gameboard.afterstates().into_iter()
+            .fold(None, |best: Option<(MoveDirection, usize, usize)>, (direction, outcome)| {
+                let points = outcome.points;
+                let empty = empty_count(&outcome.cells);
+                match best {
+                    Some((_, best_points, best_empty)) if (best_points, best_empty) >= (points, empty) => best,
+                    _ => Some((direction, points, empty)),
+                }
+            })
+            .map(|(direction, _, _)| direction)
+    }
+}
+
+/// Keeps the big tiles in the bottom left corner by trying down, left,
+/// right and up, in that order.
+pub struct Corner;
+
+/// Order in which `Corner` tries moves.
+const CORNER_ORDER: [MoveDirection; 4] =
+    [MoveDirection::Down, MoveDirection::Left, MoveDirection::Right, MoveDirection::Up];
+
+impl Strategy for Corner {
+    fn name(&self) -> String {
+        "corner".to_string()
+    }
+
+    fn choose(&mut self, gameboard: &Gameboard) -> Option<MoveDirection> {
+        CORNER_ORDER.iter().cloned().find(|&direction| gameboard.is_legal(direction))
+    }
+}
+
+impl<H: Heuristic> Strategy for Expectimax<H> {
+    fn name(&self) -> String {
+        format!("expectimax-{}", self.depth)
+    }
+
+    fn choose(&mut self, gameboard: &Gameboard) -> Option<MoveDirection> {
+        self.best_move(gameboard)
+    }
+}
+
+/// Plays every legal move followed by random games, and takes the move
+/// whose games score the most on average.
+pub struct MonteCarlo<P: Strategy = RandomStrategy> {
+    /// Games played after each candidate move.
+    pub rollouts: usize,
+    /// Moves played in each game before it is scored, or `None` to play
+    /// until the game is lost.
+    pub rollout_depth: Option<usize>,
+    /// Picks the moves inside the games.
+    pub policy: P,
+    /// Seeds the spawns inside the games.
+    pub rng: GameRng,
+}
+
+impl MonteCarlo {
+    /// Creates a player with 50 random rollouts per move, each 40 moves deep.
+    pub fn new(seed: u64) -> MonteCarlo {
+        let mut rng = GameRng::new(seed);
+        let policy = RandomStrategy::new(rng.next_u64());
+        MonteCarlo::with_policy(policy, rng)
+    }
+}
+
+impl<P: Strategy> MonteCarlo<P> {
+    /// Creates a player whose rollouts are played by `policy`.
+    pub fn with_policy(policy: P, rng: GameRng) -> MonteCarlo<P> {
+        MonteCarlo {
+            rollouts: 50,
+            rollout_depth: Some(40),
+            policy: policy,
+            rng: rng,
+        }
+    }
+
+    /// Average score gained by playing `direction` and then a rollout.
+    fn evaluate(&mut self, gameboard: &Gameboard, direction: MoveDirection) -> f64 {
+        let mut total = 0;
+        for _ in 0..self.rollouts {
+            let mut rollout = gameboard.clone();
+            rollout.rng = GameRng::new(self.rng.next_u64());
+            let mut game_state = rollout.handle_move(direction);
+            let mut depth = 0;
+            while game_state != GameState::Lost && self.rollout_depth.is_none_or(|limit| depth < limit) {
+                match self.policy.choose(&rollout) {
+                    Some(next) => game_state = rollout.handle_move(next),
+                    None => break,
+                }
+                depth += 1;
+            }
+            total += rollout.score - gameboard.score;
+        }
+        total as f64 / ::std::cmp::max(1, self.rollouts) as f64
+    }
+}
+
+impl<P: Strategy> Strategy for MonteCarlo<P> {
+    fn name(&self) -> String {
+        format!("monte-carlo-{}", self.rollouts)
+    }
+
+    fn choose(&mut self, gameboard: &Gameboard) -> Option<MoveDirection> {
+        let mut best: Option<(MoveDirection, f64)> = None;
+        for direction in gameboard.legal_moves() {
+            let value = self.evaluate(gameboard, direction);
+            if best.is_none_or(|(_, best_value)| value > best_value) {
+                best = Some((direction, value));
+            }
+        }
+        best.map(|(direction, _)| direction)
+    }
+}
+
+/// How a game played by a strategy ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameResult {
+    pub score: usize,
+    pub moves: usize,
+    pub max_tile: usize,
+    /// Whether the target tile was reached at some point.
+    pub won: bool,
+}
+
+/// Lets `strategy` play `gameboard` until it is lost, the rules end it
+/// after a win, or `max_moves` moves were made.
+pub fn play<S: Strategy + ?Sized>(strategy: &mut S, mut gameboard: Gameboard, max_moves: Option<usize>) -> GameResult {
+    let mut game_state = GameState::Playing;
+    while max_moves.is_none_or(|limit| gameboard.moves < limit) {
+        if game_state == GameState::Won {
+            game_state = gameboard.state_after_win();
+        }
+        if game_state != GameState::Playing {
+            break;
+        }
+        match strategy.choose(&gameboard) {
+            Some(direction) => game_state = gameboard.handle_move(direction),
+            None => break,
+        }
+    }
+    GameResult {
+        score: gameboard.score,
+        moves: gameboard.moves,
+        max_tile: gameboard.max_tile(),
+        won: gameboard.has_already_won,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strategy::{play, Corner, Greedy, MonteCarlo, RandomStrategy, Strategy};
+    use {Gameboard, MoveDirection};
+
+    #[test]
+    fn every_strategy_finishes_a_game() {
+        let mut monte_carlo = MonteCarlo::new(1);
+        monte_carlo.rollouts = 5;
+        monte_carlo.rollout_depth = Some(5);
+        let mut strategies: Vec<Box<dyn Strategy>> = vec![
+            Box::new(RandomStrategy::new(1)),
+            Box::new(Greedy),
+            Box::new(Corner),
+            Box::new(monte_carlo),
+        ];
+        for strategy in strategies.iter_mut() {
+            let result = play(&mut **strategy, Gameboard::with_seed(8, true), Some(300));
+            assert!(result.moves > 0, "{} made no moves", strategy.name());
+            assert!(result.max_tile >= 8);
+        }
+    }
+
+    #[test]
+    fn greedy_takes_the_biggest_merge() {
+        let gameboard: Gameboard = "2|2|0|0\n0|0|0|0\n8|0|0|0\n8|0|0|0".parse().unwrap();
+        let choice = Greedy.choose(&gameboard);
+        assert!(choice == Some(MoveDirection::Up) || choice == Some(MoveDirection::Down));
+    }
+
+    #[test]
+    fn same_seeds_play_the_same_game() {
+        let play_monte_carlo = || {
+            let mut monte_carlo = MonteCarlo::new(3);
+            monte_carlo.rollouts = 5;
+            play(&mut monte_carlo, Gameboard::with_seed(5, true), Some(20))
+        };
+        assert_eq!(play_monte_carlo(), play_monte_carlo());
+    }
+}
@@ -0,0 +1,482 @@
+//! Colors, corner radii and font of the window, loadable from files.
+//!
+//! Theme files are plain text like saves, one `key value` pair per line,
+//! starting with `2048-theme <version>`; the current version is 1. Colors
+//! are written `#rrggbb` or `#rrggbbaa`. Every key is optional and starts
+//! out as in the theme named by `base`, which should come first:
+//!
+//! ```text
+//! 2048-theme 1
+//! base dark                       (or classic, high-contrast, deuteranopia,
+//!                                 protanopia, tritanopia)
+//! name Company colors
+//! background #10131a              (around the board)
+//! board #1d2230
+//! grid #2c3344                    (lines between cells)
+//! board-edge #3b4357
+//! text #e8eaf0                    (text below the board)
+//! dialog #1d2230e6                (behind "You won!" and "You lost!")
+//! hint #5d8cffb3
+//! tile-text-dark #1a1a1a          (tiles pick whichever is easier to read)
+//! tile-text-light #fafafa
+//! tile 2 #f2b179                  (one line per tile value)
+//! tile 4 #f59563
+//! tile-beyond #400080             (tiles past the last one fade towards it)
+//! tile-radius 0.06                (fraction of the tile side)
+//! board-radius 5                  (for 100 pixel cells)
+//! font assets/FiraSans-Regular.ttf
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use palette::{self, Rgba};
+
+/// Theme file version read by this build.
+pub const THEME_VERSION: u32 = 1;
+/// Font used by the built-in themes.
+pub const DEFAULT_FONT: &'static str = "assets/FiraSans-Regular.ttf";
+
+/// How the window is drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Color around the board.
+    pub background_color: Rgba,
+    pub board_color: Rgba,
+    /// Color of the lines between cells.
+    pub grid_color: Rgba,
+    pub board_edge_color: Rgba,
+    /// Color of the text below the board.
+    pub text_color: Rgba,
+    /// Color behind the won and lost messages.
+    pub dialog_color: Rgba,
+    /// Color of the hint arrow and labels.
+    pub hint_color: Rgba,
+    /// Tile text on light tiles.
+    pub tile_text_dark: Rgba,
+    /// Tile text on dark tiles.
+    pub tile_text_light: Rgba,
+    /// Tile colors by exponent, starting with the tile 2.
+    pub tile_colors: Vec<Rgba>,
+    /// Color that tiles past the end of `tile_colors` approach.
+    pub tile_beyond: Rgba,
+    /// Corner radius of tiles, as a fraction of their side.
+    pub tile_radius: f64,
+    /// Corner radius of the board, for 100 pixel cells.
+    pub board_radius: f64,
+    /// Path of the TrueType font.
+    pub font_path: String,
+}
+
+impl Theme {
+    /// The original light orange look.
+    pub fn classic() -> Theme {
+        Theme {
+            name: "classic".to_string(),
+            background_color: [1.0, 1.0, 1.0, 1.0],
+            board_color: [1.0, 0.89, 0.8, 1.0],
+            grid_color: [0.0, 0.0, 0.2, 1.0],
+            board_edge_color: [0.0, 0.0, 0.2, 1.0],
+            text_color: [0.0, 0.0, 0.1, 1.0],
+            dialog_color: [1.0, 1.0, 1.0, 0.70],
+            hint_color: [0.1, 0.3, 0.8, 0.6],
+            tile_text_dark: [0.0, 0.0, 0.1, 1.0],
+            tile_text_light: [1.0, 1.0, 1.0, 1.0],
+            tile_colors: (1..12).map(|exponent| palette::tile_color(1 << exponent)).collect(),
+            tile_beyond: [0.3, 0.1, 0.0, 1.0],
+            tile_radius: 0.06,
+            board_radius: 5.0,
+            font_path: DEFAULT_FONT.to_string(),
+        }
+    }
+
+    /// Light tiles on a dark board.
+    pub fn dark() -> Theme {
+        Theme {
+            name: "dark".to_string(),
+            background_color: [0.09, 0.09, 0.11, 1.0],
+            board_color: [0.16, 0.16, 0.2, 1.0],
+            grid_color: [0.25, 0.25, 0.3, 1.0],
+            board_edge_color: [0.32, 0.32, 0.38, 1.0],
+            text_color: [0.88, 0.88, 0.92, 1.0],
+            dialog_color: [0.1, 0.1, 0.13, 0.9],
+            hint_color: [0.4, 0.6, 1.0, 0.7],
+            tile_colors: vec![
+                [0.27, 0.33, 0.45, 1.0],
+                [0.25, 0.38, 0.55, 1.0],
+                [0.2, 0.45, 0.65, 1.0],
+                [0.15, 0.55, 0.7, 1.0],
+                [0.15, 0.62, 0.6, 1.0],
+                [0.25, 0.68, 0.45, 1.0],
+                [0.5, 0.72, 0.3, 1.0],
+                [0.75, 0.72, 0.25, 1.0],
+                [0.88, 0.6, 0.25, 1.0],
+                [0.9, 0.45, 0.3, 1.0],
+                [0.9, 0.3, 0.4, 1.0],
+            ],
+            tile_beyond: [0.7, 0.3, 0.9, 1.0],
+            ..Theme::classic()
+        }
+    }
+
+    /// Black and white with strongly different tile colors.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            name: "high-contrast".to_string(),
+            background_color: [0.0, 0.0, 0.0, 1.0],
+            board_color: [0.0, 0.0, 0.0, 1.0],
+            grid_color: [1.0, 1.0, 1.0, 1.0],
+            board_edge_color: [1.0, 1.0, 1.0, 1.0],
+            text_color: [1.0, 1.0, 1.0, 1.0],
+            dialog_color: [0.0, 0.0, 0.0, 0.92],
+            hint_color: [1.0, 1.0, 0.0, 0.9],
+            tile_text_dark: [0.0, 0.0, 0.0, 1.0],
+            tile_colors: vec![
+                [1.0, 1.0, 1.0, 1.0],
+                [1.0, 1.0, 0.0, 1.0],
+                [0.0, 1.0, 1.0, 1.0],
+                [0.0, 1.0, 0.0, 1.0],
+                [1.0, 0.5, 0.0, 1.0],
+                [1.0, 0.0, 1.0, 1.0],
+                [0.0, 0.4, 1.0, 1.0],
+                [1.0, 0.0, 0.0, 1.0],
+                [0.6, 0.0, 0.8, 1.0],
+                [0.0, 0.5, 0.0, 1.0],
+                [0.5, 0.25, 0.0, 1.0],
+            ],
+            tile_beyond: [0.3, 0.3, 0.3, 1.0],
+            ..Theme::classic()
+        }
+    }
+
+    /// Blue and yellow tiles of alternating lightness, for players who
+    /// can't see green.
+    pub fn deuteranopia() -> Theme {
+        Theme {
+            name: "deuteranopia".to_string(),
+            board_color: [0.88, 0.88, 0.86, 1.0],
+            tile_colors: vec![
+                [0.949, 0.941, 0.902, 1.0],
+                [1.0, 0.894, 0.541, 1.0],
+                [0.651, 0.804, 0.918, 1.0],
+                [0.91, 0.659, 0.0, 1.0],
+                [0.31, 0.561, 0.839, 1.0],
+                [0.431, 0.322, 0.0, 1.0],
+                [0.11, 0.31, 0.612, 1.0],
+                [1.0, 0.824, 0.122, 1.0],
+                [0.043, 0.165, 0.369, 1.0],
+                [0.831, 0.604, 0.251, 1.0],
+                [0.082, 0.082, 0.082, 1.0],
+            ],
+            tile_beyond: [0.6, 0.6, 0.6, 1.0],
+            ..Theme::classic()
+        }
+    }
+
+    /// Like `deuteranopia`, with the yellows lightened for players who
+    /// can't see red and so see warm colors darker.
+    pub fn protanopia() -> Theme {
+        Theme {
+            name: "protanopia".to_string(),
+            tile_colors: vec![
+                [0.949, 0.941, 0.902, 1.0],
+                [1.0, 0.941, 0.541, 1.0],
+                [0.651, 0.804, 0.918, 1.0],
+                [0.941, 0.753, 0.0, 1.0],
+                [0.31, 0.561, 0.839, 1.0],
+                [0.431, 0.345, 0.0, 1.0],
+                [0.11, 0.31, 0.612, 1.0],
+                [1.0, 0.878, 0.29, 1.0],
+                [0.043, 0.165, 0.369, 1.0],
+                [0.847, 0.69, 0.376, 1.0],
+                [0.082, 0.082, 0.082, 1.0],
+            ],
+            ..Theme::deuteranopia()
+        }
+    }
+
+    /// Red and cyan tiles of alternating lightness, for players who can't
+    /// see blue.
+    pub fn tritanopia() -> Theme {
+        Theme {
+            name: "tritanopia".to_string(),
+            tile_colors: vec![
+                [0.949, 0.941, 0.902, 1.0],
+                [1.0, 0.761, 0.8, 1.0],
+                [0.525, 0.863, 0.863, 1.0],
+                [0.878, 0.188, 0.227, 1.0],
+                [0.0, 0.627, 0.659, 1.0],
+                [0.549, 0.0, 0.063, 1.0],
+                [0.0, 0.353, 0.376, 1.0],
+                [1.0, 0.502, 0.584, 1.0],
+                [0.0, 0.188, 0.235, 1.0],
+                [0.361, 0.0, 0.125, 1.0],
+                [0.082, 0.082, 0.082, 1.0],
+            ],
+            ..Theme::deuteranopia()
+        }
+    }
+
+    /// Themes that need no file, in the order they are switched through.
+    pub fn built_in() -> Vec<Theme> {
+        vec![
+            Theme::classic(),
+            Theme::dark(),
+            Theme::high_contrast(),
+            Theme::deuteranopia(),
+            Theme::protanopia(),
+            Theme::tritanopia(),
+        ]
+    }
+
+    /// Built-in theme called `name`, if any.
+    pub fn by_name(name: &str) -> Option<Theme> {
+        Theme::built_in().into_iter().find(|theme| theme.name == name)
+    }
+
+    /// Background color of a tile with value `n`.
+    ///
+    /// Tiles past the end of `tile_colors` get halfway closer to
+    /// `tile_beyond` with every doubling, so all values stay apart.
+    pub fn tile_color(&self, n: usize) -> Rgba {
+        let index = exponent(n).saturating_sub(1);
+        if index < self.tile_colors.len() {
+            return self.tile_colors[index];
+        }
+        let last = match self.tile_colors.last() {
+            Some(&last) => last,
+            None => return self.tile_beyond,
+        };
+        let steps = (index + 1 - self.tile_colors.len()) as i32;
+        let t = 1.0 - 0.5f32.powi(steps);
+        let mut color = [0.0; 4];
+        for (channel, (&from, &to)) in color.iter_mut().zip(last.iter().zip(self.tile_beyond.iter())) {
+            *channel = from + (to - from) * t;
+        }
+        color
+    }
+
+    /// Text color that is easiest to read on a tile with value `n`.
+    pub fn tile_text_color(&self, n: usize) -> Rgba {
+        let tile = luminance(self.tile_color(n));
+        let dark = (tile - luminance(self.tile_text_dark)).abs();
+        let light = (tile - luminance(self.tile_text_light)).abs();
+        if dark >= light { self.tile_text_dark } else { self.tile_text_light }
+    }
+
+    /// Loads a theme file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Theme, ThemeError> {
+        let file = File::open(path)?;
+        Theme::read(BufReader::new(file))
+    }
+
+    /// Reads a theme in the format described in the module documentation.
+    pub fn read<R: BufRead>(r: R) -> Result<Theme, ThemeError> {
+        let mut theme = Theme::classic();
+        let mut lines = r.lines().enumerate()
+            .map(|(i, line)| line.map(|line| (i + 1, line.trim().to_string())));
+        let header = loop {
+            match lines.next() {
+                Some(Ok((_, ref line))) if line.is_empty() => (),
+                Some(line) => break line?,
+                None => return Err(format_error(1, "empty theme file".to_string())),
+            }
+        };
+        match header.1.split_whitespace().collect::<Vec<_>>()[..] {
+            ["2048-theme", version] if version.parse::<u32>().ok() == Some(THEME_VERSION) => (),
+            ["2048-theme", version] => return Err(format_error(header.0, format!("unsupported theme version {}", version))),
+            _ => return Err(format_error(header.0, "expected `2048-theme`".to_string())),
+        }
+        for line in lines {
+            let (number, line) = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("").trim();
+            let color = || parse_color(value).ok_or_else(|| format_error(number, format!("invalid color `{}`", value)));
+            let number_value = || value.parse::<f64>().map_err(|_| format_error(number, format!("invalid {} `{}`", key, value)));
+            match key {
+                "base" => {
+                    theme = Theme::by_name(value)
+                        .ok_or_else(|| format_error(number, format!("unknown theme `{}`", value)))?;
+                }
+                "name" => theme.name = value.to_string(),
+                "background" => theme.background_color = color()?,
+                "board" => theme.board_color = color()?,
+                "grid" => theme.grid_color = color()?,
+                "board-edge" => theme.board_edge_color = color()?,
+                "text" => theme.text_color = color()?,
+                "dialog" => theme.dialog_color = color()?,
+                "hint" => theme.hint_color = color()?,
+                "tile-text-dark" => theme.tile_text_dark = color()?,
+                "tile-text-light" => theme.tile_text_light = color()?,
+                "tile-beyond" => theme.tile_beyond = color()?,
+                "tile-radius" => theme.tile_radius = number_value()?,
+                "board-radius" => theme.board_radius = number_value()?,
+                "font" => theme.font_path = value.to_string(),
+                "tile" => {
+                    let mut parts = value.splitn(2, ' ');
+                    let tile = parts.next().and_then(|n| n.parse::<usize>().ok())
+                        .filter(|n| n.is_power_of_two() && *n >= 2)
+                        .ok_or_else(|| format_error(number, format!("invalid tile `{}`", value)))?;
+                    let tile_color = parse_color(parts.next().unwrap_or("").trim())
+                        .ok_or_else(|| format_error(number, format!("invalid color in `{}`", value)))?;
+                    theme.set_tile_color(tile, tile_color);
+                }
+                _ => return Err(format_error(number, format!("unknown key `{}`", key))),
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Sets the color of tile `n`, filling any gap before it with the
+    /// colors those tiles had.
+    fn set_tile_color(&mut self, n: usize, color: Rgba) {
+        let index = exponent(n) - 1;
+        while self.tile_colors.len() <= index {
+            let next = self.tile_color(2 << self.tile_colors.len());
+            self.tile_colors.push(next);
+        }
+        self.tile_colors[index] = color;
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::classic()
+    }
+}
+
+/// Power of two of `n`, rounded down.
+fn exponent(n: usize) -> usize {
+    if n == 0 { 0 } else { (usize::BITS - 1 - n.leading_zeros()) as usize }
+}
+
+/// Perceived brightness, from 0 for black to 1 for white.
+fn luminance(color: Rgba) -> f32 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`.
+fn parse_color(text: &str) -> Option<Rgba> {
+    let digits = text.strip_prefix('#')?;
+    if !(digits.len() == 6 || digits.len() == 8) || !digits.is_ascii() {
+        return None;
+    }
+    let mut color = [1.0; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(digits.len() / 2) {
+        let byte = u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).ok()?;
+        *channel = byte as f32 / 255.0;
+    }
+    Some(color)
+}
+
+/// Error from loading a theme.
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(io::Error),
+    /// The theme is malformed at the given line, counted from 1.
+    Format { line: usize, message: String },
+}
+
+fn format_error(line: usize, message: String) -> ThemeError {
+    ThemeError::Format {
+        line: line,
+        message: message,
+    }
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThemeError::Io(ref err) => write!(f, "{}", err),
+            ThemeError::Format { line, ref message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ThemeError {}
+
+impl From<io::Error> for ThemeError {
+    fn from(err: io::Error) -> ThemeError {
+        ThemeError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use palette::{distance, simulate, ColorBlindness};
+    use theme::{Theme, ThemeError};
+
+    #[test]
+    fn file_overrides_its_base() {
+        let text = "\
+2048-theme 1
+base dark
+name Demo
+board #ff000080
+tile 4 #00ff00
+tile 64 #0000ff
+font fonts/Brand.ttf
+";
+        let theme = Theme::read(text.as_bytes()).unwrap();
+        let dark = Theme::dark();
+        assert_eq!(theme.name, "Demo");
+        assert_eq!(theme.board_color, [1.0, 0.0, 0.0, 128.0 / 255.0]);
+        assert_eq!(theme.text_color, dark.text_color);
+        assert_eq!(theme.tile_color(2), dark.tile_color(2));
+        assert_eq!(theme.tile_color(4), [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(theme.tile_color(64), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(theme.font_path, "fonts/Brand.ttf");
+
+        match Theme::read("2048-theme 1\nboard red\n".as_bytes()) {
+            Err(ThemeError::Format { line: 2, .. }) => (),
+            other => panic!("expected a format error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tiles_past_the_table_keep_changing() {
+        for theme in Theme::built_in() {
+            let colors: Vec<_> = (1..20).map(|exponent| theme.tile_color(1 << exponent)).collect();
+            for pair in colors.windows(2) {
+                assert!(pair[0] != pair[1], "{} repeats a tile color", theme.name);
+            }
+        }
+    }
+
+    #[test]
+    fn colorblind_themes_keep_tiles_apart() {
+        let themes = [
+            (Theme::protanopia(), ColorBlindness::Protanopia),
+            (Theme::deuteranopia(), ColorBlindness::Deuteranopia),
+            (Theme::tritanopia(), ColorBlindness::Tritanopia),
+        ];
+        for &(ref theme, kind) in themes.iter() {
+            let seen: Vec<_> = (1..12).map(|exponent| simulate(theme.tile_color(1 << exponent), kind)).collect();
+            for (i, &a) in seen.iter().enumerate() {
+                for (j, &b) in seen.iter().enumerate().skip(i + 1) {
+                    assert!(distance(a, b) > 0.15, "{}: tiles {} and {} look alike", theme.name, 2 << i, 2 << j);
+                }
+            }
+        }
+        // The classic 16, 32 and 64 are the shades that started this.
+        let classic = Theme::classic();
+        let sixteen = simulate(classic.tile_color(16), ColorBlindness::Deuteranopia);
+        let thirty_two = simulate(classic.tile_color(32), ColorBlindness::Deuteranopia);
+        assert!(distance(sixteen, thirty_two) < 0.15);
+    }
+
+    #[test]
+    fn tile_text_contrasts_with_the_tile() {
+        let theme = Theme::high_contrast();
+        assert_eq!(theme.tile_text_color(2), theme.tile_text_dark);
+        assert_eq!(theme.tile_text_color(256), theme.tile_text_light);
+    }
+}